@@ -0,0 +1,3 @@
+fn main() {
+    0b101u64 + 0b11u64;
+}
@@ -0,0 +1,26 @@
+fn compose(f: fn(i64) -> i64, g: fn(i64) -> i64) -> fn(i64) -> i64 {
+    |x: i64| g(f(x))
+}
+
+fn map(values: [i64], f: fn(i64) -> i64) -> [i64] {
+    let result: [i64] = [];
+    let i = 0;
+    while i < len(values) {
+        push(result, f(values[i]));
+        i += 1;
+    }
+    result
+}
+
+fn negate(x: i64) -> i64 {
+    -x
+}
+
+fn main() -> i64 {
+    let offset = 10;
+    let shift = |x: i64| x + offset;
+    let both = compose(shift, negate);
+
+    let values = map([1, 2, 3], both);
+    values[0] + values[1] + values[2]
+}
@@ -0,0 +1,32 @@
+enum Shape {
+    Circle(f64),
+    Rect(f64, f64),
+    Empty,
+}
+
+enum Option {
+    Some(Shape),
+    None,
+}
+
+fn area(shape: Option) -> f64 {
+    match shape {
+        Option::Some(Shape::Circle(r)) => 3.14 * r * r,
+        Option::Some(Shape::Rect(w, h)) => w * h,
+        Option::Some(Shape::Empty) => 0.0,
+        Option::None => 0.0,
+    }
+}
+
+fn main() -> i64 {
+    let shapes = [Option::Some(Shape::Rect(2.0, 3.0)), Option::None];
+    let total = area(shapes[0]) + area(shapes[1]);
+
+    match total == 6.0 {
+        true => {
+            println("six");
+            6
+        }
+        false => 0,
+    }
+}
@@ -1 +1,11 @@
-0b101u64 + 0b11u64;
+fn main() {
+    22.0 / 7.0;
+    2 + 2 == 4;
+    123 + 321;
+
+    {
+        12345 * 54321;
+        0x101u64 + 0x11u64;
+        0o1000 - 0x100;
+    }
+}
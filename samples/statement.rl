@@ -1,4 +1,5 @@
-{
+fn main() -> i64 {
+    let a: i64 = 0;
     22.0 / 7.0;
     2 + 2 == 4;
     123 + 321;
@@ -8,4 +9,6 @@
         0x101u64 + 0x11u64;
         0o1000 - 0x100;
     }
+
+    a
 }
@@ -0,0 +1,13 @@
+fn greet(name: str) -> str {
+    "Hello, " + name + "!"
+}
+
+fn main() {
+    let greeting = greet("world");
+    println(greeting);
+    println(r#"raw strings keep "quotes" and \n as is"#);
+
+    if len(greeting) > 10 && "abc" < "abd" {
+        println("strings compare\tlexicographically");
+    }
+}
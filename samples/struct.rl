@@ -0,0 +1,19 @@
+struct Account {
+    owner: str,
+    balance: i64,
+}
+
+fn deposit(account: Account, amount: i64) {
+    account.balance += amount;
+}
+
+fn main() -> i64 {
+    let account = Account { owner: "rail", balance: 10 };
+    deposit(account, 32);
+
+    if account.balance > 40 {
+        println(account.owner + " is rich");
+    }
+
+    account.balance
+}
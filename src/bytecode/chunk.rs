@@ -1,56 +1,119 @@
-use crate::{bytecode::OpCode, runtime::Value};
+use crate::{bytecode::OpCode, grammar::Span, runtime::Value};
 
 #[derive(Debug)]
 pub struct Chunk {
     bytes: Vec<u8>,
-    lines: Vec<usize>,
+    /// Offsets where the source span changes, each span covers the code up to the next one
+    spans: Vec<(usize, Span)>,
     consts: Vec<Value>,
+    /// String literals, allocated on the VM heap each time `StrConst` runs
+    strings: Vec<String>,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chunk {
     pub fn new() -> Self {
         Self {
             bytes: Vec::new(),
-            lines: Vec::new(),
+            spans: Vec::new(),
             consts: Vec::new(),
+            strings: Vec::new(),
         }
     }
 
-    /// Low-level helper: append a single byte with its source line.
-    fn push_byte(&mut self, byte: u8, line: usize) {
+    /// Low-level helper: append a single byte with its source span.
+    fn push_byte(&mut self, byte: u8, span: Span) {
+        if self.spans.last().is_none_or(|(_, last)| *last != span) {
+            self.spans.push((self.bytes.len(), span));
+        }
         self.bytes.push(byte);
-        self.lines.push(line);
     }
     /// Append a u16 operand in big-endian form.
-    fn push_u16(&mut self, value: u16, line: usize) {
+    fn push_u16(&mut self, value: u16, span: Span) {
         let [hi, lo] = value.to_be_bytes();
-        self.push_byte(hi, line);
-        self.push_byte(lo, line);
+        self.push_byte(hi, span);
+        self.push_byte(lo, span);
     }
 
-    pub fn add_instruction(&mut self, op: OpCode, line: usize) {
-        self.bytes.push(op as u8);
-        self.lines.push(line);
+    pub fn add_instruction(&mut self, op: OpCode, span: Span) {
+        self.push_byte(op as u8, span);
     }
 
-    pub fn add_const(&mut self, value: Value, line: usize) {
+    /// Append an instruction followed by its u16 operand.
+    pub fn add_instruction_u16(&mut self, op: OpCode, operand: u16, span: Span) {
+        self.add_instruction(op, span);
+        self.push_u16(operand, span);
+    }
+
+    /// Append an instruction followed by two u16 operands.
+    pub fn add_instruction_u16_u16(&mut self, op: OpCode, first: u16, second: u16, span: Span) {
+        self.add_instruction(op, span);
+        self.push_u16(first, span);
+        self.push_u16(second, span);
+    }
+
+    /// Append a forward jump with a placeholder offset.
+    /// Returns the position of the offset to be fixed by `patch_jump`.
+    pub fn add_jump(&mut self, op: OpCode, span: Span) -> usize {
+        self.add_instruction_u16(op, u16::MAX, span);
+        self.len() - 2
+    }
+
+    /// Point the jump operand at `at` to the end of the code stream.
+    pub fn patch_jump(&mut self, at: usize) {
+        let offset: u16 = (self.len() - at - 2)
+            .try_into()
+            .expect("Too much code to jump over");
+        let [hi, lo] = offset.to_be_bytes();
+        self.bytes[at] = hi;
+        self.bytes[at + 1] = lo;
+    }
+
+    /// Append a backward jump to `start`.
+    pub fn add_loop(&mut self, start: usize, span: Span) {
+        let offset: u16 = (self.len() + 3 - start)
+            .try_into()
+            .expect("Loop body too large");
+        self.add_instruction_u16(OpCode::Loop, offset, span);
+    }
+
+    pub fn add_const(&mut self, value: Value, span: Span) {
         let idx: u16 = self.consts.len().try_into().expect("Too many constants");
         self.consts.push(value);
-        self.add_instruction(OpCode::Const, line);
-        self.push_u16(idx, line);
+        self.add_instruction(OpCode::Const, span);
+        self.push_u16(idx, span);
     }
 
-    pub fn add_int64(&mut self, i: i64, line: usize) {
-        self.add_const(Value::Int64(i), line)
+    pub fn add_int64(&mut self, i: i64, span: Span) {
+        self.add_const(Value::Int64(i), span)
+    }
+    pub fn add_uint64(&mut self, u: u64, span: Span) {
+        self.add_const(Value::Uint64(u), span);
+    }
+    pub fn add_float64(&mut self, f: f64, span: Span) {
+        self.add_const(Value::Float64(f), span);
     }
-    pub fn add_uint64(&mut self, u: u64, line: usize) {
-        self.add_const(Value::Uint64(u), line);
+    pub fn add_bool(&mut self, b: bool, span: Span) {
+        self.add_const(Value::Bool(b), span);
     }
-    pub fn add_float64(&mut self, f: f64, line: usize) {
-        self.add_const(Value::Float64(f), line);
+    pub fn add_unit(&mut self, span: Span) {
+        self.add_const(Value::Unit, span);
     }
-    pub fn add_bool(&mut self, b: bool, line: usize) {
-        self.add_const(Value::Bool(b), line);
+    pub fn add_str(&mut self, s: &str, span: Span) {
+        let idx = match self.strings.iter().position(|known| known == s) {
+            Some(idx) => idx,
+            None => {
+                self.strings.push(s.to_owned());
+                self.strings.len() - 1
+            }
+        };
+        let idx = idx.try_into().expect("Too many strings");
+        self.add_instruction_u16(OpCode::StrConst, idx, span);
     }
 
     /// Number of bytes in the code stream
@@ -58,8 +121,14 @@ impl Chunk {
         self.bytes.len()
     }
 
-    pub fn get_line(&self, idx: usize) -> usize {
-        self.lines[idx]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Source span of the code the byte belongs to
+    pub fn get_span(&self, idx: usize) -> Span {
+        let run = self.spans.partition_point(|(start, _)| *start <= idx);
+        self.spans[run - 1].1
     }
 
     pub fn get_byte(&self, idx: usize) -> u8 {
@@ -69,4 +138,29 @@ impl Chunk {
     pub fn get_const(&self, idx: usize) -> Value {
         self.consts[idx]
     }
+
+    pub fn get_string(&self, idx: usize) -> &str {
+        &self.strings[idx]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_cover_the_code_up_to_the_next_one() {
+        let (a, b) = (Span::new(0, 0, 5), Span::new(0, 6, 9));
+        let mut chunk = Chunk::new();
+        chunk.add_int64(1, a);
+        chunk.add_int64(2, a);
+        chunk.add_instruction(OpCode::I64Add, b);
+        chunk.add_instruction(OpCode::Return, a);
+
+        assert_eq!(chunk.spans.len(), 3);
+        let spans = (0..chunk.len())
+            .map(|at| chunk.get_span(at))
+            .collect::<Vec<_>>();
+        assert_eq!(spans, [a, a, a, a, a, a, b, a]);
+    }
 }
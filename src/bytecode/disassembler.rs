@@ -9,8 +9,8 @@ pub fn disassemble(chunk: &Chunk) -> Vec<String> {
         } else {
             format!("{:#04x} byte", byte)
         };
-        let line = chunk.get_line(i);
-        let out = format!("{i:0>4}: line: {line:0>4} - {code}");
+        let span = chunk.get_span(i);
+        let out = format!("{i:0>4}: span: {}..{} - {code}", span.start, span.end);
         result.push(out);
     }
     result
@@ -3,4 +3,5 @@ mod disassembler;
 mod opcode;
 
 pub use chunk::Chunk;
+pub use disassembler::disassemble;
 pub use opcode::OpCode;
@@ -33,6 +33,7 @@ define_opcodes! {
     Const = 0,
     True = 1,
     False = 2,
+    StrConst = 3,
 
     GetLocal = 10,
     SetLocal = 11,
@@ -77,11 +78,88 @@ define_opcodes! {
     F64Greater = 58,
     F64GreaterEqual = 59,
 
+    I64Rem = 60,
+    U64Rem = 61,
+    F64Rem = 62,
+    I64Neg = 63,
+    F64Neg = 64,
+
+    StrConcat = 70,
+    StrEqual = 71,
+    StrNotEqual = 72,
+    StrLess = 73,
+    StrLessEqual = 74,
+    StrGreater = 75,
+    StrGreaterEqual = 76,
+    StrLen = 77,
+
     BoolNot = 80,
 
+    // integers keep their two's complement bits, floats truncate toward zero
+    // and saturate at the bounds of the integer type with NaN as 0
+    I64ToU64 = 81,
+    I64ToF64 = 82,
+    U64ToI64 = 83,
+    U64ToF64 = 84,
+    F64ToI64 = 85,
+    F64ToU64 = 86,
+
+    MakeTuple = 100,
+    GetField = 101,
+    Unpack = 102,
+    MakeArray = 103,
+    GetIndex = 104,
+    SetIndex = 105,
+    ArrayPush = 106,
+    ArrayPop = 107,
+    ArrayLen = 108,
+    MakeStruct = 109,
+    SetField = 110,
+    MakeVariant = 111,
+    IsVariant = 112,
+    MakeClosure = 113,
+    MakeNative = 114,
+
+    // shift amounts outside of 0..64 are an error, `>>` is arithmetic
+    // for i64 and logical for u64
+    I64BitAnd = 120,
+    I64BitOr = 121,
+    I64BitXor = 122,
+    I64Shl = 123,
+    I64Shr = 124,
+    I64BitNot = 125,
+
+    U64BitAnd = 130,
+    U64BitOr = 131,
+    U64BitXor = 132,
+    U64Shl = 133,
+    U64Shr = 134,
+    U64BitNot = 135,
+
+    // `+` and friends are checked, these define the result on overflow
+    I64WrappingAdd = 140,
+    I64WrappingSub = 141,
+    I64WrappingMul = 142,
+    I64SaturatingAdd = 143,
+    I64SaturatingSub = 144,
+    I64SaturatingMul = 145,
+
+    U64WrappingAdd = 150,
+    U64WrappingSub = 151,
+    U64WrappingMul = 152,
+    U64SaturatingAdd = 153,
+    U64SaturatingSub = 154,
+    U64SaturatingMul = 155,
+
     Pop = 90,
     Return = 91,
     Call = 92,
+    Panic = 93,
+    Print = 94,
+    Dup2 = 95,
+    Dup = 96,
+    CallValue = 97,
+    CallNative = 98,
 }
 
 impl Display for OpCode {
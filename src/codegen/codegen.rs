@@ -6,38 +6,300 @@ use crate::module::Module;
 use crate::runtime::*;
 use crate::semantic::*;
 
+/// Local variable living in a stack slot of the current frame
 #[derive(Debug)]
-pub struct CodeGen {}
+struct Local {
+    name: String,
+    depth: usize,
+}
+
+/// Enclosing loop that `break` and `continue` jump out of
+#[derive(Debug)]
+struct LoopTarget {
+    label: Option<String>,
+    /// Start of the condition, target of `continue`
+    start: usize,
+    /// Temporaries on the stack when entering the loop, the ones above are popped on exit
+    depth: usize,
+    /// Jumps from `break` to be patched to the loop exit
+    exits: Vec<usize>,
+}
+
+/// Assignment target, with the array and index or the object already on the stack
+#[derive(Debug, Clone, Copy)]
+enum Place {
+    Local(u16),
+    Element,
+    Field(u16),
+}
+
+/// Hidden local holding the value a `match` is testing, a keyword so no identifier can name it
+const SCRUTINEE: &str = "match";
+
+/// Compilation state of the function a lambda is nested in, restored after the lambda
+#[derive(Debug)]
+struct Enclosing {
+    locals: Vec<Local>,
+    slot_count: usize,
+    scope_depth: usize,
+    temporaries: usize,
+    loops: Vec<LoopTarget>,
+}
+
+#[derive(Debug, Default)]
+pub struct CodeGen {
+    /// Live locals in slot order, slot `i` is `stack_base + i`
+    locals: Vec<Local>,
+    /// Slots the current function reserves, at least the most ever live at once
+    slot_count: usize,
+    scope_depth: usize,
+    /// Values on the stack above the locals, operands of expressions still being evaluated
+    temporaries: usize,
+    loops: Vec<LoopTarget>,
+    /// Function indices in `Program.functions` by name
+    functions: HashMap<String, u16>,
+    /// Host function indices in `Program.natives` by name
+    natives: HashMap<String, u16>,
+    /// Global constants, inlined at every use
+    consts: HashMap<String, Value>,
+    /// Field names of structs in declaration order, which is their layout
+    structs: HashMap<String, Vec<String>>,
+    /// Variant names of enums in declaration order, which gives their tags
+    enums: HashMap<String, Vec<String>>,
+    /// Captured locals of every lambda
+    captures: HashMap<expression::Id, Vec<String>>,
+    /// Compiled lambdas, placed after the named functions in `Program.functions`
+    lambdas: Vec<Function>,
+}
 
 impl CodeGen {
     pub fn new() -> Self {
-        Self {}
+        Self::default()
     }
 
-    pub fn compile(&mut self, module: Module) -> Program {
-        let mut chunk = Chunk::new();
+    /// Last character of the node of the span, for code emitted after it
+    fn end(span: Span) -> Span {
+        Span::new(
+            span.file,
+            span.end.saturating_sub(1).max(span.start),
+            span.end,
+        )
+    }
 
-        self.compile_statement(
-            &module.syntax.arena,
-            &module.types,
-            &mut chunk,
-            module.syntax.arena.get_root(),
-        );
+    fn begin_scope(&mut self) {
+        self.scope_depth += 1;
+    }
 
-        chunk.add_instruction(OpCode::Return, 100);
+    /// Drop locals of the innermost scope, their slots are reused by later locals
+    fn end_scope(&mut self) {
+        self.scope_depth -= 1;
+        while let Some(local) = self.locals.last() {
+            if local.depth <= self.scope_depth {
+                break;
+            }
+            self.locals.pop();
+        }
+    }
 
-        let main_fn = Function {
-            name: "main".to_string(),
-            chunk,
-            arity: 0,
+    fn declare_local(&mut self, name: &str) -> u16 {
+        let local = Local {
+            name: name.to_owned(),
+            depth: self.scope_depth,
+        };
+        self.locals.push(local);
+        self.slot_count = self.slot_count.max(self.locals.len());
+        (self.locals.len() - 1).try_into().expect("Too many locals")
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<u16> {
+        let slot = self.locals.iter().rposition(|local| local.name == name)?;
+        Some(slot.try_into().expect("Too many locals"))
+    }
+
+    /// Slot of a field in the struct that `object` evaluates to
+    fn field_index(
+        &self,
+        types: &HashMap<expression::Id, Type>,
+        object: expression::Id,
+        field: &str,
+    ) -> u16 {
+        let Type::Struct(name) = &types[&object] else {
+            unreachable!("field access is checked by typechecker");
         };
+        let idx = self.structs[name]
+            .iter()
+            .position(|known| known == field)
+            .expect("fields are checked by typechecker");
+        idx.try_into().expect("Too many fields")
+    }
+
+    fn variant_tag(&self, enum_name: &str, variant: &str) -> u16 {
+        let tag = self.enums[enum_name]
+            .iter()
+            .position(|known| known == variant)
+            .expect("variants are checked by typechecker");
+        tag.try_into().expect("Too many variants")
+    }
+
+    /// Innermost loop, or the one with a matching label
+    fn find_loop(&mut self, label: &Option<String>) -> &mut LoopTarget {
+        self.loops
+            .iter_mut()
+            .rev()
+            .find(|l| label.is_none() || l.label == *label)
+            .expect("loop labels are resolved by typechecker")
+    }
+
+    pub fn compile(&mut self, module: Module) -> Program {
+        let arena = &module.syntax.arena;
+        self.consts = module.consts;
+        self.captures = module.captures;
+
+        for id in arena.item_ids() {
+            match &arena[id].kind {
+                item::Kind::Struct { name, fields } => {
+                    let fields = fields.iter().map(|field| field.name.clone()).collect();
+                    self.structs.insert(name.clone(), fields);
+                }
+                item::Kind::Enum { name, variants } => {
+                    let variants = variants.iter().map(|v| v.name.clone()).collect();
+                    self.enums.insert(name.clone(), variants);
+                }
+                _ => (),
+            }
+        }
+
+        // indices first, so calls can refer to functions defined later
+        let functions = arena
+            .item_ids()
+            .filter(|id| matches!(arena[*id].kind, item::Kind::Function { .. }))
+            .collect::<Vec<_>>();
+        for id in &functions {
+            let item::Kind::Function { name, .. } = &arena[*id].kind else {
+                unreachable!("filtered above");
+            };
+            let idx = self.functions.len().try_into().expect("Too many functions");
+            self.functions.insert(name.clone(), idx);
+        }
 
         let mut program = Program::new();
-        program.functions.push(main_fn);
+        program.files = module.syntax.files.clone();
+        for native in module.natives {
+            let idx = program.natives.len().try_into().expect("Too many natives");
+            self.natives.insert(native.name.clone(), idx);
+            program.natives.push(native);
+        }
+        for id in functions {
+            let function = self.compile_function(arena, &module.types, id);
+            program.functions.push(function);
+        }
+        program.functions.append(&mut self.lambdas);
+        program.entry = self.functions["main"] as usize;
 
         program
     }
 
+    fn compile_function(
+        &mut self,
+        arena: &Arena,
+        types: &HashMap<expression::Id, Type>,
+        id: item::Id,
+    ) -> Function {
+        let item::Kind::Function {
+            name, params, body, ..
+        } = &arena[id].kind
+        else {
+            unreachable!("only functions are compiled");
+        };
+        let span = Self::end(arena[*body].span);
+
+        self.locals.clear();
+        self.slot_count = 0;
+        self.temporaries = 0;
+        self.begin_scope();
+        for param in params {
+            self.declare_local(&param.name);
+        }
+
+        let mut chunk = Chunk::new();
+        self.compile_expr(arena, types, &mut chunk, *body);
+        chunk.add_instruction(OpCode::Return, span);
+        self.end_scope();
+
+        Function {
+            name: name.clone(),
+            chunk,
+            arity: params.len().try_into().expect("Too many parameters"),
+            locals: (self.slot_count - params.len())
+                .try_into()
+                .expect("Too many locals"),
+        }
+    }
+
+    /// Compile a lambda into its own function and create a closure of it
+    ///
+    /// The closure carries the captured locals, which the function
+    /// receives as extra arguments after its parameters
+    fn compile_lambda(
+        &mut self,
+        arena: &Arena,
+        types: &HashMap<expression::Id, Type>,
+        chunk: &mut Chunk,
+        id: expression::Id,
+        params: &[item::Param],
+        body: expression::Id,
+    ) {
+        let span = Self::end(arena[body].span);
+        let captures = self.captures[&id].clone();
+
+        let enclosing = Enclosing {
+            locals: std::mem::take(&mut self.locals),
+            slot_count: std::mem::take(&mut self.slot_count),
+            scope_depth: std::mem::take(&mut self.scope_depth),
+            temporaries: std::mem::take(&mut self.temporaries),
+            loops: std::mem::take(&mut self.loops),
+        };
+
+        self.begin_scope();
+        for name in params.iter().map(|param| &param.name).chain(&captures) {
+            self.declare_local(name);
+        }
+        let arity = params.len() + captures.len();
+
+        let mut lambda = Chunk::new();
+        self.compile_expr(arena, types, &mut lambda, body);
+        lambda.add_instruction(OpCode::Return, span);
+        self.end_scope();
+
+        let function = Function {
+            name: "<lambda>".to_owned(),
+            chunk: lambda,
+            arity: arity.try_into().expect("Too many parameters"),
+            locals: (self.slot_count - arity)
+                .try_into()
+                .expect("Too many locals"),
+        };
+
+        self.locals = enclosing.locals;
+        self.slot_count = enclosing.slot_count;
+        self.scope_depth = enclosing.scope_depth;
+        self.temporaries = enclosing.temporaries;
+        self.loops = enclosing.loops;
+
+        let idx = self.functions.len() + self.lambdas.len();
+        self.lambdas.push(function);
+
+        for name in &captures {
+            let slot = self
+                .resolve_local(name)
+                .expect("captures are enclosing locals");
+            chunk.add_instruction_u16(OpCode::GetLocal, slot, span);
+        }
+        let idx = idx.try_into().expect("Too many functions");
+        let len = captures.len().try_into().expect("Too many captures");
+        chunk.add_instruction_u16_u16(OpCode::MakeClosure, idx, len, span);
+    }
+
     fn compile_statement(
         &mut self,
         arena: &Arena,
@@ -50,20 +312,170 @@ impl CodeGen {
 
         let node = &arena[id];
         let kind = &node.kind;
-        let line = 42;
+        let span = node.span;
 
         match kind {
             Expression(exp) => {
                 self.compile_expr(arena, types, chunk, *exp);
-                chunk.add_instruction(Pop, line);
+                chunk.add_instruction(Pop, span);
             }
-            Block(stmts) => {
-                for stmt in stmts {
-                    self.compile_statement(arena, types, chunk, *stmt);
+            Let { pattern, init, .. } => {
+                self.compile_expr(arena, types, chunk, *init);
+                self.compile_binding(arena, chunk, *pattern);
+            }
+        };
+        // both consume the value
+        self.temporaries -= 1;
+    }
+
+    /// Store the value on top of the stack into fresh locals of the pattern
+    ///
+    /// The value must match, literals and variant tags are not tested here
+    fn compile_binding(&mut self, arena: &Arena, chunk: &mut Chunk, id: pattern::Id) {
+        let span = arena[id].span;
+
+        match &arena[id].kind {
+            pattern::Kind::Identifier(name) => {
+                let slot = self.declare_local(name);
+                chunk.add_instruction_u16(OpCode::SetLocal, slot, span);
+            }
+            pattern::Kind::Wildcard | pattern::Kind::Literal(_) => {
+                chunk.add_instruction(OpCode::Pop, span);
+            }
+            pattern::Kind::Error => unreachable!("trees with syntax errors are not compiled"),
+            pattern::Kind::Tuple(elements)
+            | pattern::Kind::Variant {
+                fields: elements, ..
+            } if elements.is_empty() => {
+                chunk.add_instruction(OpCode::Pop, span);
+            }
+            pattern::Kind::Tuple(elements)
+            | pattern::Kind::Variant {
+                fields: elements, ..
+            } => {
+                let len = elements.len().try_into().expect("Too many tuple elements");
+                chunk.add_instruction_u16(OpCode::Unpack, len, span);
+                // last element ends up on top, names are unique within a pattern
+                for element in elements.iter().rev() {
+                    self.compile_binding(arena, chunk, *element);
                 }
             }
-            _ => unimplemented!(),
+        }
+    }
+
+    /// Test whether the value in `slot` matches the pattern, returns the jumps taken when not
+    ///
+    /// `path` holds the field indices leading from that value to the one under test
+    fn compile_pattern_test(
+        &mut self,
+        arena: &Arena,
+        types: &HashMap<expression::Id, Type>,
+        chunk: &mut Chunk,
+        id: pattern::Id,
+        slot: u16,
+        path: &mut Vec<u16>,
+    ) -> Vec<usize> {
+        let span = arena[id].span;
+        let load = |chunk: &mut Chunk, path: &[u16]| {
+            chunk.add_instruction_u16(OpCode::GetLocal, slot, span);
+            for index in path {
+                chunk.add_instruction_u16(OpCode::GetField, *index, span);
+            }
         };
+
+        match &arena[id].kind {
+            pattern::Kind::Identifier(_) | pattern::Kind::Wildcard => Vec::new(),
+            pattern::Kind::Error => unreachable!("trees with syntax errors are not compiled"),
+            pattern::Kind::Literal(literal) => {
+                load(chunk, path);
+                match &arena[*literal].kind {
+                    // a bool is its own test
+                    expression::Kind::Bool(true) => (),
+                    expression::Kind::Bool(false) => chunk.add_instruction(OpCode::BoolNot, span),
+                    _ => {
+                        self.compile_expr(arena, types, chunk, *literal);
+                        let ty = &types[literal];
+                        Self::compile_infix_op(chunk, operator::Infix::Equal, ty, ty, span);
+                    }
+                }
+                vec![chunk.add_jump(OpCode::JumpIfFalse, span)]
+            }
+            pattern::Kind::Tuple(elements) => {
+                let mut fails = Vec::new();
+                for (index, element) in elements.iter().enumerate() {
+                    path.push(index.try_into().expect("Too many tuple elements"));
+                    fails.extend(
+                        self.compile_pattern_test(arena, types, chunk, *element, slot, path),
+                    );
+                    path.pop();
+                }
+                fails
+            }
+            pattern::Kind::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                load(chunk, path);
+                let tag = self.variant_tag(enum_name, variant);
+                chunk.add_instruction_u16(OpCode::IsVariant, tag, span);
+                let mut fails = vec![chunk.add_jump(OpCode::JumpIfFalse, span)];
+                // fields are only read once the tag is known to match
+                for (index, field) in fields.iter().enumerate() {
+                    path.push(index.try_into().expect("Too many fields"));
+                    fails
+                        .extend(self.compile_pattern_test(arena, types, chunk, *field, slot, path));
+                    path.pop();
+                }
+                fails
+            }
+        }
+    }
+
+    /// Test the arms in order, the first matching one binds its names and evaluates its body
+    fn compile_match(
+        &mut self,
+        arena: &Arena,
+        types: &HashMap<expression::Id, Type>,
+        chunk: &mut Chunk,
+        scrutinee: expression::Id,
+        arms: &[expression::Arm],
+    ) {
+        let span = arena[scrutinee].span;
+
+        self.begin_scope();
+        self.compile_expr(arena, types, chunk, scrutinee);
+        let slot = self.declare_local(SCRUTINEE);
+        chunk.add_instruction_u16(OpCode::SetLocal, slot, span);
+        self.temporaries -= 1;
+        let depth = self.temporaries;
+
+        let mut to_end = Vec::new();
+        for arm in arms {
+            let to_next =
+                self.compile_pattern_test(arena, types, chunk, arm.pattern, slot, &mut Vec::new());
+
+            self.begin_scope();
+            chunk.add_instruction_u16(OpCode::GetLocal, slot, span);
+            self.compile_binding(arena, chunk, arm.pattern);
+            self.temporaries = depth;
+            self.compile_expr(arena, types, chunk, arm.body);
+            self.end_scope();
+            to_end.push(chunk.add_jump(OpCode::Jump, span));
+
+            for jump in to_next {
+                chunk.patch_jump(jump);
+            }
+        }
+
+        // arms are exhaustive, so only a diverging scrutinee gets here and this is never run
+        chunk.add_instruction(OpCode::Panic, span);
+        chunk.add_unit(span);
+
+        for jump in to_end {
+            chunk.patch_jump(jump);
+        }
+        self.end_scope();
     }
 
     fn compile_expr(
@@ -79,69 +491,224 @@ impl CodeGen {
         let node = &arena[id];
         let kind = &node.kind;
         let ty = types.get(&id).unwrap();
-        let line = 42;
+        let span = node.span;
+        // every expression leaves its value on top of the operands before it
+        let temporaries = self.temporaries;
 
         match kind {
-            Int64(i) => chunk.add_int64(*i, line),
-            Uint64(u) => chunk.add_uint64(*u, line),
-            Float64(f) => chunk.add_float64(*f, line),
-            Bool(b) => chunk.add_bool(*b, line),
+            Int64(i) => chunk.add_int64(*i, span),
+            Uint64(u) => chunk.add_uint64(*u, span),
+            Float64(f) => chunk.add_float64(*f, span),
+            Bool(b) => chunk.add_bool(*b, span),
+            Error => unreachable!("trees with syntax errors are not compiled"),
+            Str(s) => chunk.add_str(s, span),
+            Unit => chunk.add_unit(span),
+            Identifier(name) => match self.resolve_local(name) {
+                Some(slot) => chunk.add_instruction_u16(GetLocal, slot, span),
+                None => match self.consts.get(name) {
+                    Some(value) => chunk.add_const(*value, span),
+                    // a named function as a value is a closure without captures
+                    None => match self.functions.get(name) {
+                        Some(idx) => chunk.add_instruction_u16_u16(MakeClosure, *idx, 0, span),
+                        None => chunk.add_instruction_u16(MakeNative, self.natives[name], span),
+                    },
+                },
+            },
+
+            Tuple(elements) => {
+                for element in elements {
+                    self.compile_expr(arena, types, chunk, *element);
+                }
+                let len = elements.len().try_into().expect("Too many tuple elements");
+                chunk.add_instruction_u16(MakeTuple, len, span);
+            }
+
+            TupleIndex { tuple, index } => {
+                self.compile_expr(arena, types, chunk, *tuple);
+                let index = (*index).try_into().expect("Too many tuple elements");
+                chunk.add_instruction_u16(GetField, index, span);
+            }
+
+            StructLiteral { name, fields } => {
+                let len = self.structs[name].len();
+                let len = len.try_into().expect("Too many fields");
+                chunk.add_instruction_u16(MakeStruct, len, span);
+                // fields are evaluated in source order and stored by layout
+                for (field, value) in fields {
+                    chunk.add_instruction(Dup, span);
+                    self.temporaries = temporaries + 2;
+                    self.compile_expr(arena, types, chunk, *value);
+                    let idx = self.field_index(types, id, field);
+                    chunk.add_instruction_u16(SetField, idx, span);
+                }
+            }
+
+            Field { object, name } => {
+                self.compile_expr(arena, types, chunk, *object);
+                let idx = self.field_index(types, *object, name);
+                chunk.add_instruction_u16(GetField, idx, span);
+            }
+
+            Variant {
+                enum_name,
+                variant,
+                args,
+            } => {
+                for arg in args {
+                    self.compile_expr(arena, types, chunk, *arg);
+                }
+                let tag = self.variant_tag(enum_name, variant);
+                let len = args.len().try_into().expect("Too many fields");
+                chunk.add_instruction_u16_u16(MakeVariant, tag, len, span);
+            }
+
+            Array(elements) => {
+                for element in elements {
+                    self.compile_expr(arena, types, chunk, *element);
+                }
+                let len = elements.len().try_into().expect("Too many array elements");
+                chunk.add_instruction_u16(MakeArray, len, span);
+            }
+
+            Index { array, index } => {
+                self.compile_expr(arena, types, chunk, *array);
+                self.compile_expr(arena, types, chunk, *index);
+                chunk.add_instruction(GetIndex, span);
+            }
+
+            Infix { lhs, rhs, op } if op.is_assignment() => {
+                self.compile_assignment(arena, types, chunk, *op, *lhs, *rhs);
+            }
+
+            Infix { lhs, rhs, op } if op.is_lazy() => {
+                self.compile_lazy(arena, types, chunk, *op, *lhs, *rhs);
+            }
 
             Infix { lhs, rhs, op } => {
                 self.compile_expr(arena, types, chunk, *lhs);
                 self.compile_expr(arena, types, chunk, *rhs);
                 let lty = types.get(lhs).unwrap();
                 let rty = types.get(rhs).unwrap();
+                Self::compile_infix_op(chunk, *op, lty, rty, span);
+            }
 
-                use Type::*;
-                use operator::Infix::*;
-
-                match (lty, op, rty) {
-                    (Int64, Plus, Int64) => chunk.add_instruction(I64Add, line),
-                    (Int64, Minus, Int64) => chunk.add_instruction(I64Sub, line),
-                    (Int64, Mul, Int64) => chunk.add_instruction(I64Mul, line),
-                    (Int64, Div, Int64) => chunk.add_instruction(I64Div, line),
-
-                    (Int64, Equal, Int64) => chunk.add_instruction(I64Equal, line),
-                    (Int64, NotEqual, Int64) => chunk.add_instruction(I64NotEqual, line),
-                    (Int64, Less, Int64) => chunk.add_instruction(I64Less, line),
-                    (Int64, LessEqual, Int64) => chunk.add_instruction(I64LessEqual, line),
-                    (Int64, Greater, Int64) => chunk.add_instruction(I64Greater, line),
-                    (Int64, GreaterEqual, Int64) => chunk.add_instruction(I64GreaterEqual, line),
-
-                    (Uint64, Plus, Uint64) => chunk.add_instruction(U64Add, line),
-                    (Uint64, Minus, Uint64) => chunk.add_instruction(U64Sub, line),
-                    (Uint64, Mul, Uint64) => chunk.add_instruction(U64Mul, line),
-                    (Uint64, Div, Uint64) => chunk.add_instruction(U64Div, line),
-
-                    (Uint64, Equal, Uint64) => chunk.add_instruction(U64Equal, line),
-                    (Uint64, NotEqual, Uint64) => chunk.add_instruction(U64NotEqual, line),
-                    (Uint64, Less, Uint64) => chunk.add_instruction(U64Less, line),
-                    (Uint64, LessEqual, Uint64) => chunk.add_instruction(U64LessEqual, line),
-                    (Uint64, Greater, Uint64) => chunk.add_instruction(U64Greater, line),
-                    (Uint64, GreaterEqual, Uint64) => chunk.add_instruction(U64GreaterEqual, line),
-
-                    (Float64, Plus, Float64) => chunk.add_instruction(F64Add, line),
-                    (Float64, Minus, Float64) => chunk.add_instruction(F64Sub, line),
-                    (Float64, Mul, Float64) => chunk.add_instruction(F64Mul, line),
-                    (Float64, Div, Float64) => chunk.add_instruction(F64Div, line),
-
-                    (Float64, Equal, Float64) => chunk.add_instruction(F64Equal, line),
-                    (Float64, NotEqual, Float64) => chunk.add_instruction(F64NotEqual, line),
-                    (Float64, Less, Float64) => chunk.add_instruction(F64Less, line),
-                    (Float64, LessEqual, Float64) => chunk.add_instruction(F64LessEqual, line),
-                    (Float64, Greater, Float64) => chunk.add_instruction(F64Greater, line),
-                    (Float64, GreaterEqual, Float64) => {
-                        chunk.add_instruction(F64GreaterEqual, line)
+            Block { stmts, tail } => {
+                self.begin_scope();
+                for stmt in stmts {
+                    self.compile_statement(arena, types, chunk, *stmt);
+                }
+                match tail {
+                    Some(tail) => self.compile_expr(arena, types, chunk, *tail),
+                    None => chunk.add_unit(span),
+                }
+                self.end_scope();
+            }
+
+            If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                self.compile_expr(arena, types, chunk, *cond);
+                let to_else = chunk.add_jump(JumpIfFalse, span);
+
+                self.temporaries = temporaries;
+                self.compile_expr(arena, types, chunk, *then);
+                let to_end = chunk.add_jump(Jump, span);
+
+                chunk.patch_jump(to_else);
+                self.temporaries = temporaries;
+                match otherwise {
+                    Some(otherwise) => self.compile_expr(arena, types, chunk, *otherwise),
+                    None => chunk.add_unit(span),
+                }
+                chunk.patch_jump(to_end);
+            }
+
+            While { label, cond, body } => {
+                let start = chunk.len();
+                self.compile_expr(arena, types, chunk, *cond);
+                let to_exit = chunk.add_jump(JumpIfFalse, span);
+
+                self.loops.push(LoopTarget {
+                    label: label.clone(),
+                    start,
+                    depth: temporaries,
+                    exits: vec![to_exit],
+                });
+                self.temporaries = temporaries;
+                self.compile_expr(arena, types, chunk, *body);
+                chunk.add_instruction(Pop, span);
+                chunk.add_loop(start, span);
+
+                let exits = self.loops.pop().expect("pushed above").exits;
+                for exit in exits {
+                    chunk.patch_jump(exit);
+                }
+                chunk.add_unit(span);
+            }
+
+            Match { scrutinee, arms } => {
+                self.compile_match(arena, types, chunk, *scrutinee, arms);
+            }
+
+            Break { label } => {
+                let depth = self.find_loop(label).depth;
+                self.pop_temporaries(chunk, depth, span);
+                let exit = chunk.add_jump(Jump, span);
+                self.find_loop(label).exits.push(exit);
+                chunk.add_unit(span);
+            }
+
+            Continue { label } => {
+                let LoopTarget { start, depth, .. } = *self.find_loop(label);
+                self.pop_temporaries(chunk, depth, span);
+                chunk.add_loop(start, span);
+                chunk.add_unit(span);
+            }
+
+            expression::Kind::Call { callee, args } => match &arena[*callee].kind {
+                Identifier(name)
+                    if self.resolve_local(name).is_none() && !self.consts.contains_key(name) =>
+                {
+                    for arg in args {
+                        self.compile_expr(arena, types, chunk, *arg);
+                    }
+                    let argc = args.len().try_into().expect("Too many arguments");
+                    match (self.functions.get(name), self.natives.get(name)) {
+                        (Some(idx), _) => chunk.add_instruction_u16(OpCode::Call, *idx, span),
+                        (None, Some(idx)) => {
+                            chunk.add_instruction_u16_u16(CallNative, *idx, argc, span)
+                        }
+                        (None, None) => Self::compile_builtin(types, chunk, name, args, span),
                     }
+                }
+                _ => {
+                    self.compile_expr(arena, types, chunk, *callee);
+                    for arg in args {
+                        self.compile_expr(arena, types, chunk, *arg);
+                    }
+                    let argc = args.len().try_into().expect("Too many arguments");
+                    chunk.add_instruction_u16(CallValue, argc, span);
+                }
+            },
+
+            Lambda { params, body, .. } => {
+                self.compile_lambda(arena, types, chunk, id, params, *body);
+            }
 
-                    _ => unimplemented!(
-                        "no codegen for {:?} with operand types {:?} and {:?}",
-                        op,
-                        lty,
-                        rty
-                    ),
+            expression::Kind::Return(value) => {
+                match value {
+                    Some(value) => self.compile_expr(arena, types, chunk, *value),
+                    None => chunk.add_unit(span),
                 }
+                chunk.add_instruction(OpCode::Return, span);
+                chunk.add_unit(span);
+            }
+
+            Cast { exp, .. } => {
+                self.compile_expr(arena, types, chunk, *exp);
+                Self::compile_cast(chunk, &types[exp], ty, span);
             }
 
             Prefix { op, exp } => {
@@ -156,21 +723,767 @@ impl CodeGen {
                         unreachable!("Prefix Plus is elided in ast building")
                     }
                     (Minus, Int64) => {
-                        chunk.add_int64(-1, line);
-                        chunk.add_instruction(I64Mul, line);
+                        chunk.add_instruction(I64Neg, span);
                     }
                     (Minus, Float64) => {
-                        chunk.add_float64(-1.0, line);
-                        chunk.add_instruction(F64Mul, line);
+                        chunk.add_instruction(F64Neg, span);
                     }
                     (Negate, Bool) => {
-                        chunk.add_instruction(BoolNot, line);
+                        chunk.add_instruction(BoolNot, span);
+                    }
+                    (BitNot, Int64) => {
+                        chunk.add_instruction(I64BitNot, span);
+                    }
+                    (BitNot, Uint64) => {
+                        chunk.add_instruction(U64BitNot, span);
                     }
 
                     _ => unimplemented!("no codegen for {:?} with type {:?}", op, ty),
                 }
             }
-            _ => unimplemented!(),
         };
+        self.temporaries = temporaries + 1;
+    }
+
+    /// Pop the temporaries above `depth`, before jumping out of the expressions they belong to
+    fn pop_temporaries(&self, chunk: &mut Chunk, depth: usize, span: Span) {
+        for _ in depth..self.temporaries {
+            chunk.add_instruction(OpCode::Pop, span);
+        }
+    }
+
+    /// Emit the conversion between numeric types, none when they are the same
+    fn compile_cast(chunk: &mut Chunk, from: &Type, to: &Type, span: Span) {
+        use OpCode::*;
+        use Type::*;
+
+        match (from, to) {
+            (Int64, Uint64) => chunk.add_instruction(I64ToU64, span),
+            (Int64, Float64) => chunk.add_instruction(I64ToF64, span),
+            (Uint64, Int64) => chunk.add_instruction(U64ToI64, span),
+            (Uint64, Float64) => chunk.add_instruction(U64ToF64, span),
+            (Float64, Int64) => chunk.add_instruction(F64ToI64, span),
+            (Float64, Uint64) => chunk.add_instruction(F64ToU64, span),
+            (from, to) if from == to => (),
+            _ => unimplemented!("no codegen for cast from {from:?} to {to:?}"),
+        }
+    }
+
+    /// Emit the opcode of a non-assigning infix operator for typed operands
+    fn compile_infix_op(
+        chunk: &mut Chunk,
+        op: operator::Infix,
+        lty: &Type,
+        rty: &Type,
+        span: Span,
+    ) {
+        use OpCode::*;
+        use Type::*;
+        use operator::Infix::*;
+
+        match (lty, op, rty) {
+            (Int64, Plus, Int64) => chunk.add_instruction(I64Add, span),
+            (Int64, Minus, Int64) => chunk.add_instruction(I64Sub, span),
+            (Int64, Mul, Int64) => chunk.add_instruction(I64Mul, span),
+            (Int64, Div, Int64) => chunk.add_instruction(I64Div, span),
+            (Int64, Rem, Int64) => chunk.add_instruction(I64Rem, span),
+
+            (Int64, Equal, Int64) => chunk.add_instruction(I64Equal, span),
+            (Int64, NotEqual, Int64) => chunk.add_instruction(I64NotEqual, span),
+            (Int64, Less, Int64) => chunk.add_instruction(I64Less, span),
+            (Int64, LessEqual, Int64) => chunk.add_instruction(I64LessEqual, span),
+            (Int64, Greater, Int64) => chunk.add_instruction(I64Greater, span),
+            (Int64, GreaterEqual, Int64) => chunk.add_instruction(I64GreaterEqual, span),
+
+            (Uint64, Plus, Uint64) => chunk.add_instruction(U64Add, span),
+            (Uint64, Minus, Uint64) => chunk.add_instruction(U64Sub, span),
+            (Uint64, Mul, Uint64) => chunk.add_instruction(U64Mul, span),
+            (Uint64, Div, Uint64) => chunk.add_instruction(U64Div, span),
+            (Uint64, Rem, Uint64) => chunk.add_instruction(U64Rem, span),
+
+            (Uint64, Equal, Uint64) => chunk.add_instruction(U64Equal, span),
+            (Uint64, NotEqual, Uint64) => chunk.add_instruction(U64NotEqual, span),
+            (Uint64, Less, Uint64) => chunk.add_instruction(U64Less, span),
+            (Uint64, LessEqual, Uint64) => chunk.add_instruction(U64LessEqual, span),
+            (Uint64, Greater, Uint64) => chunk.add_instruction(U64Greater, span),
+            (Uint64, GreaterEqual, Uint64) => chunk.add_instruction(U64GreaterEqual, span),
+
+            (Int64, WrappingPlus, Int64) => chunk.add_instruction(I64WrappingAdd, span),
+            (Int64, WrappingMinus, Int64) => chunk.add_instruction(I64WrappingSub, span),
+            (Int64, WrappingMul, Int64) => chunk.add_instruction(I64WrappingMul, span),
+            (Int64, SaturatingPlus, Int64) => chunk.add_instruction(I64SaturatingAdd, span),
+            (Int64, SaturatingMinus, Int64) => chunk.add_instruction(I64SaturatingSub, span),
+            (Int64, SaturatingMul, Int64) => chunk.add_instruction(I64SaturatingMul, span),
+
+            (Uint64, WrappingPlus, Uint64) => chunk.add_instruction(U64WrappingAdd, span),
+            (Uint64, WrappingMinus, Uint64) => chunk.add_instruction(U64WrappingSub, span),
+            (Uint64, WrappingMul, Uint64) => chunk.add_instruction(U64WrappingMul, span),
+            (Uint64, SaturatingPlus, Uint64) => chunk.add_instruction(U64SaturatingAdd, span),
+            (Uint64, SaturatingMinus, Uint64) => chunk.add_instruction(U64SaturatingSub, span),
+            (Uint64, SaturatingMul, Uint64) => chunk.add_instruction(U64SaturatingMul, span),
+
+            (Int64, BitAnd, Int64) => chunk.add_instruction(I64BitAnd, span),
+            (Int64, BitOr, Int64) => chunk.add_instruction(I64BitOr, span),
+            (Int64, BitXor, Int64) => chunk.add_instruction(I64BitXor, span),
+            (Int64, Shl, Int64) => chunk.add_instruction(I64Shl, span),
+            (Int64, Shr, Int64) => chunk.add_instruction(I64Shr, span),
+
+            (Uint64, BitAnd, Uint64) => chunk.add_instruction(U64BitAnd, span),
+            (Uint64, BitOr, Uint64) => chunk.add_instruction(U64BitOr, span),
+            (Uint64, BitXor, Uint64) => chunk.add_instruction(U64BitXor, span),
+            (Uint64, Shl, Uint64) => chunk.add_instruction(U64Shl, span),
+            (Uint64, Shr, Uint64) => chunk.add_instruction(U64Shr, span),
+
+            (Float64, Plus, Float64) => chunk.add_instruction(F64Add, span),
+            (Float64, Minus, Float64) => chunk.add_instruction(F64Sub, span),
+            (Float64, Mul, Float64) => chunk.add_instruction(F64Mul, span),
+            (Float64, Div, Float64) => chunk.add_instruction(F64Div, span),
+            (Float64, Rem, Float64) => chunk.add_instruction(F64Rem, span),
+
+            (Float64, Equal, Float64) => chunk.add_instruction(F64Equal, span),
+            (Float64, NotEqual, Float64) => chunk.add_instruction(F64NotEqual, span),
+            (Float64, Less, Float64) => chunk.add_instruction(F64Less, span),
+            (Float64, LessEqual, Float64) => chunk.add_instruction(F64LessEqual, span),
+            (Float64, Greater, Float64) => chunk.add_instruction(F64Greater, span),
+            (Float64, GreaterEqual, Float64) => chunk.add_instruction(F64GreaterEqual, span),
+
+            (Str, Plus, Str) => chunk.add_instruction(StrConcat, span),
+            (Str, Equal, Str) => chunk.add_instruction(StrEqual, span),
+            (Str, NotEqual, Str) => chunk.add_instruction(StrNotEqual, span),
+            (Str, Less, Str) => chunk.add_instruction(StrLess, span),
+            (Str, LessEqual, Str) => chunk.add_instruction(StrLessEqual, span),
+            (Str, Greater, Str) => chunk.add_instruction(StrGreater, span),
+            (Str, GreaterEqual, Str) => chunk.add_instruction(StrGreaterEqual, span),
+
+            _ => unimplemented!(
+                "no codegen for {:?} with operand types {:?} and {:?}",
+                op,
+                lty,
+                rty
+            ),
+        }
+    }
+
+    /// Built-in call with its arguments already on the stack
+    fn compile_builtin(
+        types: &HashMap<expression::Id, Type>,
+        chunk: &mut Chunk,
+        name: &str,
+        args: &[expression::Id],
+        span: Span,
+    ) {
+        use OpCode::*;
+
+        let arg_types = args.iter().map(|arg| &types[arg]).collect::<Vec<_>>();
+
+        match (name, arg_types.as_slice()) {
+            ("panic", _) => {
+                chunk.add_instruction(Panic, span);
+                chunk.add_unit(span);
+            }
+            ("println", _) => chunk.add_instruction(Print, span),
+            ("len", [Type::Str]) => chunk.add_instruction(StrLen, span),
+            ("len", _) => chunk.add_instruction(ArrayLen, span),
+            ("push", _) => chunk.add_instruction(ArrayPush, span),
+            ("pop", _) => chunk.add_instruction(ArrayPop, span),
+            _ => unreachable!("callees are resolved by typechecker"),
+        }
+    }
+
+    /// `&&` and `||` skip the right operand once the left one decides the result
+    fn compile_lazy(
+        &mut self,
+        arena: &Arena,
+        types: &HashMap<expression::Id, Type>,
+        chunk: &mut Chunk,
+        op: operator::Infix,
+        lhs: expression::Id,
+        rhs: expression::Id,
+    ) {
+        let span = arena[lhs].span;
+
+        self.compile_expr(arena, types, chunk, lhs);
+        let to_false = chunk.add_jump(OpCode::JumpIfFalse, span);
+        self.temporaries -= 1;
+
+        match op {
+            operator::Infix::And => {
+                self.compile_expr(arena, types, chunk, rhs);
+                let to_end = chunk.add_jump(OpCode::Jump, span);
+                chunk.patch_jump(to_false);
+                chunk.add_instruction(OpCode::False, span);
+                chunk.patch_jump(to_end);
+            }
+            operator::Infix::Or => {
+                chunk.add_instruction(OpCode::True, span);
+                let to_end = chunk.add_jump(OpCode::Jump, span);
+                chunk.patch_jump(to_false);
+                self.compile_expr(arena, types, chunk, rhs);
+                chunk.patch_jump(to_end);
+            }
+            _ => unreachable!("{op:?} is not a lazy operator"),
+        }
+    }
+
+    /// `target = value` or `target op= value`, always evaluating to `()`
+    fn compile_assignment(
+        &mut self,
+        arena: &Arena,
+        types: &HashMap<expression::Id, Type>,
+        chunk: &mut Chunk,
+        op: operator::Infix,
+        target: expression::Id,
+        value: expression::Id,
+    ) {
+        let span = arena[target].span;
+
+        // evaluate the place, leaving whatever the store needs on the stack
+        let place = match &arena[target].kind {
+            expression::Kind::Identifier(name) => {
+                let slot = self
+                    .resolve_local(name)
+                    .expect("constants are not assignable");
+                Place::Local(slot)
+            }
+            expression::Kind::Index { array, index } => {
+                self.compile_expr(arena, types, chunk, *array);
+                self.compile_expr(arena, types, chunk, *index);
+                Place::Element
+            }
+            expression::Kind::Field { object, name } => {
+                self.compile_expr(arena, types, chunk, *object);
+                Place::Field(self.field_index(types, *object, name))
+            }
+            _ => unreachable!("assignment targets are checked by typechecker"),
+        };
+
+        match op.compound_op() {
+            Some(op) => {
+                match place {
+                    Place::Local(slot) => chunk.add_instruction_u16(OpCode::GetLocal, slot, span),
+                    Place::Element => {
+                        chunk.add_instruction(OpCode::Dup2, span);
+                        chunk.add_instruction(OpCode::GetIndex, span);
+                    }
+                    Place::Field(idx) => {
+                        chunk.add_instruction(OpCode::Dup, span);
+                        chunk.add_instruction_u16(OpCode::GetField, idx, span);
+                    }
+                }
+                // the current value of the target
+                self.temporaries += 1;
+                self.compile_expr(arena, types, chunk, value);
+                let tty = types.get(&target).unwrap();
+                let vty = types.get(&value).unwrap();
+                Self::compile_infix_op(chunk, op, tty, vty, span);
+            }
+            None => self.compile_expr(arena, types, chunk, value),
+        }
+
+        match place {
+            Place::Local(slot) => chunk.add_instruction_u16(OpCode::SetLocal, slot, span),
+            Place::Element => chunk.add_instruction(OpCode::SetIndex, span),
+            Place::Field(idx) => chunk.add_instruction_u16(OpCode::SetField, idx, span),
+        }
+        chunk.add_unit(span);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Renderer;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::typechecker::Typer;
+    use crate::vm::{RuntimeError, Vm};
+
+    fn run(source: &str) -> i64 {
+        run_with(&TypeEnv::new(), source).expect("vm run failed")
+    }
+
+    fn run_with(env: &TypeEnv, source: &str) -> crate::vm::Result<i64> {
+        run_traced(env, source).map_err(|trap| trap.error)
+    }
+
+    /// Like `run_with`, keeping where a runtime error happened
+    fn run_traced(env: &TypeEnv, source: &str) -> Result<i64, RuntimeError> {
+        let syntax = Parser::new(Lexer::new(source))
+            .parse()
+            .expect("should parse");
+        let module = Typer::new(env).check(syntax).expect("should typecheck");
+        let program = CodeGen::new().compile(module);
+        Vm::from(&program).run()
+    }
+
+    #[test]
+    fn recursive_calls_return_values() {
+        let source = "
+            fn fib(n: i64) -> i64 {
+                if n < 2 {
+                    return n;
+                }
+                fib(n - 1) + fib(n - 2)
+            }
+
+            fn main() -> i64 {
+                fib(10)
+            }
+        ";
+        assert_eq!(run(source), 55);
+    }
+
+    #[test]
+    fn arguments_are_locals_of_callee() {
+        let source = "
+            fn main() -> i64 {
+                let a = 1;
+                let b = scale(a + 1, 2.5);
+                a + b
+            }
+
+            fn scale(n: i64, by: f64) -> i64 {
+                let result = 0;
+                while n > 0 {
+                    result += 10;
+                    n -= 1;
+                }
+                if by > 2.0 { result } else { 0 }
+            }
+        ";
+        assert_eq!(run(source), 21);
+    }
+
+    #[test]
+    fn loops_with_labels() {
+        let source = "
+            fn main() -> i64 {
+                let i = 0;
+                let sum = 0;
+                'outer: while i < 10 {
+                    i += 1;
+                    if i == 3 { continue; }
+                    let j = 0;
+                    while true {
+                        j += 1;
+                        if j > i { break; }
+                        if sum > 100 { break 'outer; }
+                        sum += j;
+                    }
+                }
+                sum
+            }
+        ";
+        assert_eq!(run(source), 106);
+    }
+
+    #[test]
+    fn jumps_out_of_loops_drop_pending_operands() {
+        // `continue` leaves `s` and `1` of the compound assignment behind, which would
+        // end up below the result of the block and be added to `1000` instead
+        let source = "
+            fn main() -> i64 {
+                let i = 0;
+                let s = 0;
+                let total = 1000 + {
+                    while i < 10000 {
+                        i += 1;
+                        s += 1 + { if i % 2 == 0 { continue; } i };
+                    }
+                    s
+                };
+                total
+            }
+        ";
+        assert_eq!(run(source), 1000 + 5000 + 25_000_000);
+
+        let source = "
+            fn main() -> i64 {
+                let y = 5 + {
+                    'outer: while true {
+                        while true { let z = 7 + { if true { break 'outer; } 1 }; }
+                    }
+                    0
+                };
+                y
+            }
+        ";
+        assert_eq!(run(source), 5);
+    }
+
+    #[test]
+    fn lazy_operators_short_circuit() {
+        // the right operands would divide by zero if evaluated
+        let source = "
+            fn main() -> i64 {
+                let a = false && 1 / 0 == 1;
+                let b = true || 1 / 0 == 1;
+                let c = 1 < 2 && 2 < 3 || false;
+                if !a && b && c { 1 } else { 0 }
+            }
+        ";
+        assert_eq!(run(source), 1);
+    }
+
+    #[test]
+    fn remainder_follows_rust_semantics() {
+        let source = "
+            fn main() -> i64 {
+                let min = -9223372036854775807 - 1;
+                let ok = -7 % 3 == -1
+                    && 7 % -3 == 1
+                    && min % -1 == 0
+                    && 7u64 % 3u64 == 1u64
+                    && -7.5 % 2.0 == -1.5;
+                let a = 17;
+                a %= 5;
+                if ok { a } else { -1 }
+            }
+        ";
+        assert_eq!(run(source), 2);
+    }
+
+    #[test]
+    fn constants_are_visible_in_every_function() {
+        let source = "
+            const LIMIT: i64 = BASE * 4 + 2;
+            const BASE: i64 = 10;
+            const HALF: f64 = 1.0 / 2.0;
+
+            fn limit() -> i64 { LIMIT }
+
+            fn main() -> i64 {
+                let BASE = 1;
+                if HALF == 0.5 { limit() + BASE } else { 0 }
+            }
+        ";
+        assert_eq!(run(source), 43);
+    }
+
+    #[test]
+    fn tuples_carry_multiple_values() {
+        let source = "
+            fn div_rem(a: i64, b: i64) -> (i64, i64) {
+                (a / b, a % b)
+            }
+
+            fn main() -> i64 {
+                let (q, r) = div_rem(17, 5);
+                let nested: (bool, (i64, f64)) = (true, (q * 10, 0.5));
+                let ((), (x,), y) = ((), (r,), nested.1);
+                if nested.0 && y.0 == nested.1.0 { y.0 + x } else { 0 }
+            }
+        ";
+        assert_eq!(run(source), 32);
+    }
+
+    #[test]
+    fn panic_stops_the_vm() {
+        let source = "
+            fn check(a: i64) -> i64 {
+                if a > 10 { panic() } else { a }
+            }
+            fn main() -> i64 { check(5) + check(50) }
+        ";
+        let result = run_with(&TypeEnv::new(), source);
+        assert!(matches!(result, Err(crate::vm::Error::Panic)));
+    }
+
+    #[test]
+    fn strings_concatenate_and_compare() {
+        let source = r#"
+            fn greet(name: str) -> str {
+                "Hello, " + name + "!"
+            }
+
+            fn main() -> i64 {
+                let greeting = greet("rail");
+                println(greeting);
+                let ordered = "apple" < "banana" && greeting == "Hello, rail!";
+                if ordered && r"\n" != "\n" { len(greeting) } else { 0 }
+            }
+        "#;
+        assert_eq!(run(source), 12);
+    }
+
+    #[test]
+    fn arrays_grow_and_index() {
+        let source = "
+            fn sum(values: [i64]) -> i64 {
+                let total = 0;
+                let i = 0;
+                while i < len(values) {
+                    total += values[i];
+                    i += 1;
+                }
+                total
+            }
+
+            fn main() -> i64 {
+                let values: [i64] = [];
+                push(values, 10);
+                push(values, 20);
+                push(values, 30);
+                values[0] = 1;
+                values[1] *= 2;
+                let last = pop(values);
+                let grid = [[1, 2], [3, 4]];
+                sum(values) + last + grid[1][0]
+            }
+        ";
+        assert_eq!(run(source), 1 + 40 + 30 + 3);
+    }
+
+    #[test]
+    fn out_of_bounds_index_is_a_vm_error() {
+        let source = "fn main() -> i64 { let a = [1, 2, 3]; a[3] }";
+        let result = run_with(&TypeEnv::new(), source);
+        assert!(matches!(
+            result,
+            Err(crate::vm::Error::IndexOutOfBounds { index: 3, len: 3 })
+        ));
+    }
+
+    #[test]
+    fn struct_fields_read_and_write() {
+        let source = "
+            struct Segment { from: Point, to: Point }
+            struct Point { x: i64, y: i64 }
+
+            fn length(s: Segment) -> i64 {
+                (s.to.x - s.from.x) + (s.to.y - s.from.y)
+            }
+
+            fn main() -> i64 {
+                let origin = Point { y: 0, x: 0 };
+                let segment = Segment { from: origin, to: Point { x: 3, y: 4 } };
+                segment.to.x = 10;
+                segment.to.y *= 2;
+                if segment.from.x == 0 { length(segment) } else { 0 }
+            }
+        ";
+        assert_eq!(run(source), 18);
+    }
+
+    #[test]
+    fn match_tests_variants_and_literals() {
+        let source = "
+            enum Shape { Circle(i64), Rect(i64, i64), Empty }
+
+            fn area(s: Shape) -> i64 {
+                match s {
+                    Shape::Circle(r) => 3 * r * r,
+                    Shape::Rect(1, h) => h,
+                    Shape::Rect(w, h) => w * h,
+                    Shape::Empty => 0,
+                }
+            }
+
+            fn name(n: i64) -> str {
+                match n { 0 => \"zero\", -1 => \"minus one\", _ => \"many\" }
+            }
+
+            fn main() -> i64 {
+                let total = area(Shape::Circle(2)) + area(Shape::Rect(1, 5)) + area(Shape::Rect(3, 4));
+                let (_, empty) = (1, area(Shape::Empty));
+                let names = match (name(0), name(-1)) {
+                    (\"zero\", \"minus one\") => 100,
+                    _ => 0,
+                };
+                let flag = match 1 < 2 { false => 0, true => 1000 };
+                total + empty + names + flag
+            }
+        ";
+        assert_eq!(run(source), 12 + 5 + 12 + 100 + 1000);
+    }
+
+    #[test]
+    fn closures_capture_by_value() {
+        let source = "
+            fn adder(k: i64) -> fn(i64) -> i64 {
+                |x: i64| x + k
+            }
+
+            fn twice(f: fn(i64) -> i64, x: i64) -> i64 {
+                f(f(x))
+            }
+
+            fn square(x: i64) -> i64 { x * x }
+
+            fn main() -> i64 {
+                let add5 = adder(5);
+                let base = 100;
+                let nested = |x: i64| -> i64 {
+                    let inner = |y: i64| y + base;
+                    inner(x) * 2
+                };
+                base = 0;
+                twice(add5, 1) + twice(square, 3) + nested(1) + adder(1)(base)
+            }
+        ";
+        assert_eq!(run(source), 11 + 81 + 202 + 1);
+    }
+
+    #[test]
+    fn natives_are_called_like_functions() {
+        let mut env = TypeEnv::new();
+        env.register_native(
+            "triple",
+            vec![Type::Int64],
+            Type::Int64,
+            |_, args| match args {
+                [Value::Int64(i)] => Ok(Value::Int64(i * 3)),
+                _ => unreachable!("arguments are typechecked"),
+            },
+        )
+        .expect("triple is not registered yet");
+        env.register_native("shout", vec![Type::Str], Type::Str, |vm, args| {
+            let Object::String(s) = vm.get_object(args[0])? else {
+                unreachable!("arguments are typechecked");
+            };
+            let loud = s.to_uppercase();
+            Ok(vm.alloc(Object::String(loud)))
+        })
+        .expect("shout is not registered yet");
+        env.register_native("fail", vec![], Type::Never, |_, _| {
+            Err(crate::vm::Error::Native("host failure".to_owned()))
+        })
+        .expect("fail is not registered yet");
+
+        let source = "
+            fn apply(f: fn(i64) -> i64, x: i64) -> i64 { f(x) }
+
+            fn main() -> i64 {
+                let loud = shout(\"abc\") == \"ABC\";
+                if loud { triple(2) + apply(triple, 10) } else { 0 }
+            }
+        ";
+        assert_eq!(run_with(&env, source).expect("vm run failed"), 36);
+
+        let result = run_with(&env, "fn main() -> i64 { fail() }");
+        assert!(matches!(result, Err(crate::vm::Error::Native(msg)) if msg == "host failure"));
+    }
+
+    #[test]
+    fn casts_convert_between_numeric_types() {
+        let cases = [
+            ("((-1 as u64) / 2u64) as i64", i64::MAX),
+            ("18446744073709551615u64 as i64", -1),
+            ("(-1.5 as u64) as i64", 0),
+            ("((100000000000000000000.0 as u64) / 2u64) as i64", i64::MAX),
+            ("(0.0 / 0.0) as i64", 0),
+            ("(3 as f64 / 2 as f64 * 10.0) as i64", 15),
+            ("7u64 as f64 as i64 as u64 as i64", 7),
+        ];
+        for (expression, expected) in cases {
+            let source = format!("fn main() -> i64 {{ {expression} }}");
+            assert_eq!(run(&source), expected, "{expression}");
+        }
+    }
+
+    #[test]
+    fn bitwise_operators_follow_precedence() {
+        let cases = [
+            ("6 & 3", 2),
+            ("6 | 3", 7),
+            ("6 ^ 3", 5),
+            ("~0", -1),
+            ("(~0u64 >> 1u64) as i64", i64::MAX),
+            ("1 << 2 + 1", 8),
+            ("1 | 2 ^ 3 & 4", 3),
+            ("-16 >> 2", -4),
+            ("(18446744073709551615u64 >> 60u64) as i64", 15),
+            ("(|x: i64| x | 1)(4)", 5),
+        ];
+        for (expression, expected) in cases {
+            let source = format!("fn main() -> i64 {{ {expression} }}");
+            assert_eq!(run(&source), expected, "{expression}");
+        }
+
+        let result = run_with(&TypeEnv::new(), "fn main() -> i64 { let n = 64; 1 << n }");
+        assert!(matches!(result, Err(crate::vm::Error::ShiftOverflow)));
+
+        let result = run_with(&TypeEnv::new(), "fn main() -> i64 { let n = -1; 1 >> n }");
+        assert!(matches!(result, Err(crate::vm::Error::ShiftOverflow)));
+    }
+
+    #[test]
+    fn overflow_is_checked_unless_wrapping_or_saturating() {
+        let cases = [
+            ("max +% 1", i64::MIN),
+            ("min -% 1", i64::MAX),
+            ("max *% 2", -2),
+            ("max +| 1", i64::MAX),
+            ("min -| 1", i64::MIN),
+            ("min *| 2", i64::MIN),
+            ("(0u64 -% 1u64) as i64", -1),
+            ("(0u64 -| 1u64) as i64", 0),
+            ("1 +% 2 * 3", 7),
+        ];
+        for (expression, expected) in cases {
+            let source = format!(
+                "fn main() -> i64 {{
+                    let max = 9223372036854775807;
+                    let min = -max - 1;
+                    {expression}
+                }}"
+            );
+            assert_eq!(run(&source), expected, "{expression}");
+        }
+
+        let overflows = [
+            "fn main() -> i64 { let max = 9223372036854775807; max + 1 }",
+            "fn main() -> i64 { let max = 9223372036854775807; max * 2 }",
+            "fn main() -> i64 { let a = 0u64; a - 1u64; 0 }",
+            "fn main() -> i64 { let a = 9223372036854775807; a += 1; a }",
+        ];
+        for source in overflows {
+            let result = run_with(&TypeEnv::new(), source);
+            assert!(
+                matches!(result, Err(crate::vm::Error::IntegerOverflow(_))),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn negation_is_checked() {
+        assert_eq!(run("fn main() -> i64 { let a = 5; -a + 2 }"), -3);
+        assert_eq!(
+            run("fn main() -> i64 { let x = 2.5; (-x * 2.0) as i64 }"),
+            -5
+        );
+
+        let source = "fn main() -> i64 { let m = -9223372036854775807 - 1; -m }";
+        assert!(matches!(
+            run_with(&TypeEnv::new(), source),
+            Err(crate::vm::Error::IntegerOverflow("negate"))
+        ));
+    }
+
+    #[test]
+    fn runtime_errors_name_the_failing_function() {
+        let source = "
+            fn ratio(a: u64, b: u64) -> u64 {
+                let unused = 1;
+                a / b
+            }
+            fn main() -> i64 { ratio(1u64, 0u64) as i64 }
+        ";
+        let trap = run_traced(&TypeEnv::new(), source).expect_err("division by zero");
+        assert!(matches!(trap.error, crate::vm::Error::DivisionByZero));
+        assert_eq!(trap.function, "ratio");
+        assert_eq!(trap.line, 4);
+
+        let files = [SourceFile::new("main.rl", source)];
+        let rendered = Renderer::new(&files).render(&trap.into());
+        let expected = "\
+error: division by zero
+ --> main.rl:4:17
+  |
+4 |                 a / b
+  |                 ^^^^^ in `ratio`
+";
+        assert_eq!(rendered, expected);
     }
 }
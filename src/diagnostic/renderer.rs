@@ -0,0 +1,218 @@
+use std::fmt::Write;
+
+use super::{Diagnostic, Label, Severity};
+use crate::grammar::SourceFile;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Formats diagnostics for a terminal, quoting the source lines their labels point at
+///
+/// ```text
+/// error: mismatched types: expected `i64`, found `bool`
+///  --> main.rl:3:5
+///   |
+/// 3 |     a + true
+///   |     ^^^^^^^^ expected `i64`, found `bool`
+/// ```
+pub struct Renderer<'f> {
+    files: &'f [SourceFile],
+    color: bool,
+}
+
+impl<'f> Renderer<'f> {
+    pub fn new(files: &'f [SourceFile]) -> Self {
+        Self {
+            files,
+            color: false,
+        }
+    }
+
+    /// Highlight with ANSI escape codes
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+        let severity = diagnostic.severity.to_string();
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(&severity, self.severity_style(diagnostic.severity)),
+            self.paint(&format!(": {}", diagnostic.message), BOLD)
+        );
+
+        // labels of the primary file first, then the others in order of appearance
+        let mut labels = diagnostic.labels.iter().collect::<Vec<_>>();
+        labels.retain(|label| label.span.file < self.files.len());
+        let mut file_order = Vec::new();
+        if let Some(span) = diagnostic.primary_span() {
+            file_order.push(span.file);
+        }
+        for label in &labels {
+            if !file_order.contains(&label.span.file) {
+                file_order.push(label.span.file);
+            }
+        }
+
+        let width = labels
+            .iter()
+            .map(|label| self.files[label.span.file].line(label.span.start))
+            .max()
+            .map_or(0, |line| line.to_string().len());
+        let pad = " ".repeat(width);
+        let bar = self.paint("|", BLUE);
+
+        for (i, file) in file_order.into_iter().enumerate() {
+            let mut labels = labels
+                .iter()
+                .filter(|label| label.span.file == file)
+                .copied()
+                .collect::<Vec<_>>();
+            if labels.is_empty() {
+                continue;
+            }
+            let source = &self.files[file];
+            let first = labels
+                .iter()
+                .find(|label| label.primary)
+                .unwrap_or(&labels[0])
+                .span
+                .start;
+            let arrow = if i == 0 { "-->" } else { ":::" };
+            let _ = writeln!(
+                out,
+                "{pad}{} {}:{}:{}",
+                self.paint(arrow, BLUE),
+                source.name,
+                source.line(first),
+                source.column(first)
+            );
+            let _ = writeln!(out, "{pad} {bar}");
+
+            labels.sort_by_key(|label| (label.span.start, !label.primary));
+            let mut previous = None;
+            for label in &labels {
+                let line = source.line(label.span.start);
+                if previous != Some(line) {
+                    if previous.is_some_and(|previous| line > previous + 1) {
+                        let _ = writeln!(out, "{}", self.paint("...", BLUE));
+                    }
+                    let number = self.paint(&format!("{line:>width$}"), BLUE);
+                    let _ = writeln!(out, "{number} {bar} {}", source.line_text(line));
+                    previous = Some(line);
+                }
+                let _ = writeln!(
+                    out,
+                    "{pad} {bar} {}",
+                    self.underline(source, label, diagnostic)
+                );
+            }
+        }
+
+        if !diagnostic.notes.is_empty() || diagnostic.help.is_some() {
+            if width > 0 {
+                let _ = writeln!(out, "{pad} {bar}");
+            }
+            let equals = self.paint("=", BLUE);
+            for note in &diagnostic.notes {
+                let _ = writeln!(out, "{pad} {equals} {}: {note}", self.paint("note", BOLD));
+            }
+            if let Some(help) = &diagnostic.help {
+                let _ = writeln!(out, "{pad} {equals} {}: {help}", self.paint("help", GREEN));
+            }
+        }
+        out
+    }
+
+    /// Markers under the labeled text of its first line, followed by the message
+    fn underline(&self, source: &SourceFile, label: &Label, diagnostic: &Diagnostic) -> String {
+        let span = label.span;
+        let line = source.line(span.start);
+        let column = source.column(span.start);
+        let text = source.line_text(line);
+        let end_column = if source.line(span.end) == line {
+            source.column(span.end)
+        } else {
+            text.chars().count() + 1
+        };
+        let (marker, style) = match label.primary {
+            true => ('^', self.severity_style(diagnostic.severity)),
+            false => ('-', BLUE),
+        };
+        let markers = marker
+            .to_string()
+            .repeat(end_column.saturating_sub(column).max(1));
+        let underline = match label.message.is_empty() {
+            true => markers,
+            false => format!("{markers} {}", label.message),
+        };
+        format!(
+            "{}{}",
+            " ".repeat(column - 1),
+            self.paint(&underline, style)
+        )
+    }
+
+    fn severity_style(&self, severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => BOLD,
+        }
+    }
+
+    fn paint(&self, text: &str, style: &str) -> String {
+        match self.color {
+            true => format!("{style}{text}{RESET}"),
+            false => text.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::Span;
+
+    #[test]
+    fn labels_underline_their_source() {
+        let files = [SourceFile::new(
+            "main.rl",
+            "fn main() -> i64 {\n    let a = 1;\n    a + true\n}",
+        )];
+        let diagnostic = Diagnostic::error("cannot add `bool` to `i64`")
+            .with_primary(Span::new(0, 42, 46), "expected `i64`")
+            .with_secondary(Span::new(0, 27, 28), "`a` is an `i64`")
+            .with_help("compare with `a == 1`");
+
+        let rendered = Renderer::new(&files).render(&diagnostic);
+        let expected = "\
+error: cannot add `bool` to `i64`
+ --> main.rl:3:9
+  |
+2 |     let a = 1;
+  |         - `a` is an `i64`
+3 |     a + true
+  |         ^^^^ expected `i64`
+  |
+  = help: compare with `a == 1`
+";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn diagnostics_without_labels_are_one_line() {
+        let diagnostic = Diagnostic::error("`main` function not found");
+        assert_eq!(
+            Renderer::new(&[]).render(&diagnostic),
+            "error: `main` function not found\n"
+        );
+    }
+}
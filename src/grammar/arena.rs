@@ -6,16 +6,32 @@ use super::*;
 pub struct Arena {
     statements: Vec<statement::Node>,
     expressions: Vec<expression::Node>,
-    root: statement::Id,
+    items: Vec<item::Node>,
+    types: Vec<ty::Node>,
+    patterns: Vec<pattern::Node>,
 }
 
-impl Arena {
-    pub fn set_root(&mut self, root: statement::Id) {
-        self.root = root;
+impl Index<pattern::Id> for Arena {
+    type Output = pattern::Node;
+
+    fn index(&self, index: pattern::Id) -> &Self::Output {
+        &self.patterns[index.0]
     }
+}
 
-    pub fn get_root(&self) -> statement::Id {
-        self.root
+impl Index<ty::Id> for Arena {
+    type Output = ty::Node;
+
+    fn index(&self, index: ty::Id) -> &Self::Output {
+        &self.types[index.0]
+    }
+}
+
+impl Index<item::Id> for Arena {
+    type Output = item::Node;
+
+    fn index(&self, index: item::Id) -> &Self::Output {
+        &self.items[index.0]
     }
 }
 
@@ -46,27 +62,131 @@ impl Arena {
         uid
     }
 
-    pub(crate) fn make_int64(&mut self, i: i64) -> expression::Id {
+    pub(crate) fn make_int64(&mut self, i: i64, span: Span) -> expression::Id {
         let kind = expression::Kind::Int64(i);
-        let node = expression::Node { kind };
+        let node = expression::Node { kind, span };
         self.push_expression(node)
     }
 
-    pub(crate) fn make_uint64(&mut self, u: u64) -> expression::Id {
+    pub(crate) fn make_uint64(&mut self, u: u64, span: Span) -> expression::Id {
         let kind = expression::Kind::Uint64(u);
-        let node = expression::Node { kind };
+        let node = expression::Node { kind, span };
         self.push_expression(node)
     }
 
-    pub(crate) fn make_float64(&mut self, f: f64) -> expression::Id {
+    pub(crate) fn make_float64(&mut self, f: f64, span: Span) -> expression::Id {
         let kind = expression::Kind::Float64(f);
-        let node = expression::Node { kind };
+        let node = expression::Node { kind, span };
         self.push_expression(node)
     }
 
-    pub(crate) fn make_bool(&mut self, b: bool) -> expression::Id {
+    pub(crate) fn make_bool(&mut self, b: bool, span: Span) -> expression::Id {
         let kind = expression::Kind::Bool(b);
-        let node = expression::Node { kind };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_str(&mut self, s: String, span: Span) -> expression::Id {
+        let kind = expression::Kind::Str(s);
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_unit(&mut self, span: Span) -> expression::Id {
+        let kind = expression::Kind::Unit;
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_error(&mut self, span: Span) -> expression::Id {
+        let kind = expression::Kind::Error;
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_tuple(
+        &mut self,
+        elements: Vec<expression::Id>,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Tuple(elements);
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_tuple_index(
+        &mut self,
+        tuple: expression::Id,
+        index: usize,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::TupleIndex { tuple, index };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_variant(
+        &mut self,
+        enum_name: String,
+        variant: String,
+        args: Vec<expression::Id>,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Variant {
+            enum_name,
+            variant,
+            args,
+        };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_array(
+        &mut self,
+        elements: Vec<expression::Id>,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Array(elements);
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_index(
+        &mut self,
+        array: expression::Id,
+        index: expression::Id,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Index { array, index };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_struct_literal(
+        &mut self,
+        name: String,
+        fields: Vec<(String, expression::Id)>,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::StructLiteral { name, fields };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_field(
+        &mut self,
+        object: expression::Id,
+        name: String,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Field { object, name };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_identifier(&mut self, name: String, span: Span) -> expression::Id {
+        let kind = expression::Kind::Identifier(name);
+        let node = expression::Node { kind, span };
         self.push_expression(node)
     }
 
@@ -75,9 +195,10 @@ impl Arena {
         op: operator::Infix,
         lhs: expression::Id,
         rhs: expression::Id,
+        span: Span,
     ) -> expression::Id {
         let kind = expression::Kind::Infix { lhs, rhs, op };
-        let node = expression::Node { kind };
+        let node = expression::Node { kind, span };
         self.push_expression(node)
     }
 
@@ -85,18 +206,117 @@ impl Arena {
         &mut self,
         op: operator::Prefix,
         exp: expression::Id,
+        span: Span,
     ) -> expression::Id {
         let kind = expression::Kind::Prefix { exp, op };
-        let node = expression::Node { kind };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_cast(
+        &mut self,
+        exp: expression::Id,
+        ty: ty::Id,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Cast { exp, ty };
+        let node = expression::Node { kind, span };
         self.push_expression(node)
     }
 
-    pub(crate) fn make_postfix(
+    pub(crate) fn make_block(
         &mut self,
-        _op: operator::Postfix,
-        _exp: expression::Id,
+        stmts: Vec<statement::Id>,
+        tail: Option<expression::Id>,
+        span: Span,
     ) -> expression::Id {
-        unimplemented!()
+        let kind = expression::Kind::Block { stmts, tail };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_if(
+        &mut self,
+        cond: expression::Id,
+        then: expression::Id,
+        otherwise: Option<expression::Id>,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::If {
+            cond,
+            then,
+            otherwise,
+        };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_while(
+        &mut self,
+        label: Option<String>,
+        cond: expression::Id,
+        body: expression::Id,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::While { label, cond, body };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_match(
+        &mut self,
+        scrutinee: expression::Id,
+        arms: Vec<expression::Arm>,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Match { scrutinee, arms };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_break(&mut self, label: Option<String>, span: Span) -> expression::Id {
+        let kind = expression::Kind::Break { label };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_continue(&mut self, label: Option<String>, span: Span) -> expression::Id {
+        let kind = expression::Kind::Continue { label };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_call(
+        &mut self,
+        callee: expression::Id,
+        args: Vec<expression::Id>,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Call { callee, args };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_lambda(
+        &mut self,
+        params: Vec<item::Param>,
+        ret: Option<ty::Id>,
+        body: expression::Id,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Lambda { params, ret, body };
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
+    }
+
+    pub(crate) fn make_return(
+        &mut self,
+        value: Option<expression::Id>,
+        span: Span,
+    ) -> expression::Id {
+        let kind = expression::Kind::Return(value);
+        let node = expression::Node { kind, span };
+        self.push_expression(node)
     }
 }
 
@@ -111,15 +331,213 @@ impl Arena {
         uid
     }
 
-    pub(crate) fn push_epxression_statement(&mut self, id: expression::Id) -> statement::Id {
+    pub(crate) fn push_epxression_statement(
+        &mut self,
+        id: expression::Id,
+        span: Span,
+    ) -> statement::Id {
         let kind = statement::Kind::Expression(id);
-        let node = statement::Node { kind };
+        let node = statement::Node { kind, span };
         self.push_statement(node)
     }
 
-    pub(crate) fn push_block(&mut self, stmts: Vec<statement::Id>) -> statement::Id {
-        let kind = statement::Kind::Block(stmts);
-        let node = statement::Node { kind };
+    pub(crate) fn push_let(
+        &mut self,
+        pattern: pattern::Id,
+        ty: Option<ty::Id>,
+        init: expression::Id,
+        span: Span,
+    ) -> statement::Id {
+        let kind = statement::Kind::Let { pattern, ty, init };
+        let node = statement::Node { kind, span };
         self.push_statement(node)
     }
 }
+
+impl Arena {
+    pub fn item_count(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Top-level items in source order
+    pub(crate) fn item_ids(&self) -> impl Iterator<Item = item::Id> + use<> {
+        (0..self.item_count()).map(item::Id)
+    }
+
+    fn push_item(&mut self, node: item::Node) -> item::Id {
+        let uid = item::Id(self.item_count());
+        self.items.push(node);
+        uid
+    }
+
+    pub(crate) fn push_function(
+        &mut self,
+        name: String,
+        params: Vec<item::Param>,
+        ret: Option<ty::Id>,
+        body: expression::Id,
+        span: Span,
+    ) -> item::Id {
+        let kind = item::Kind::Function {
+            name,
+            params,
+            ret,
+            body,
+        };
+        let node = item::Node { kind, span };
+        self.push_item(node)
+    }
+
+    pub(crate) fn push_const(
+        &mut self,
+        name: String,
+        ty: ty::Id,
+        init: expression::Id,
+        span: Span,
+    ) -> item::Id {
+        let kind = item::Kind::Const { name, ty, init };
+        let node = item::Node { kind, span };
+        self.push_item(node)
+    }
+
+    pub(crate) fn push_struct(
+        &mut self,
+        name: String,
+        fields: Vec<item::Field>,
+        span: Span,
+    ) -> item::Id {
+        let kind = item::Kind::Struct { name, fields };
+        let node = item::Node { kind, span };
+        self.push_item(node)
+    }
+
+    pub(crate) fn push_enum(
+        &mut self,
+        name: String,
+        variants: Vec<item::Variant>,
+        span: Span,
+    ) -> item::Id {
+        let kind = item::Kind::Enum { name, variants };
+        let node = item::Node { kind, span };
+        self.push_item(node)
+    }
+}
+
+impl Arena {
+    pub fn type_count(&self) -> usize {
+        self.types.len()
+    }
+
+    fn push_type(&mut self, node: ty::Node) -> ty::Id {
+        let uid = ty::Id(self.type_count());
+        self.types.push(node);
+        uid
+    }
+
+    pub(crate) fn make_named_type(&mut self, name: String, span: Span) -> ty::Id {
+        let kind = ty::Kind::Named(name);
+        let node = ty::Node { kind, span };
+        self.push_type(node)
+    }
+
+    pub(crate) fn make_never_type(&mut self, span: Span) -> ty::Id {
+        let kind = ty::Kind::Never;
+        let node = ty::Node { kind, span };
+        self.push_type(node)
+    }
+
+    pub(crate) fn make_error_type(&mut self, span: Span) -> ty::Id {
+        let kind = ty::Kind::Error;
+        let node = ty::Node { kind, span };
+        self.push_type(node)
+    }
+
+    pub(crate) fn make_array_type(&mut self, element: ty::Id, span: Span) -> ty::Id {
+        let kind = ty::Kind::Array(element);
+        let node = ty::Node { kind, span };
+        self.push_type(node)
+    }
+
+    pub(crate) fn make_function_type(
+        &mut self,
+        params: Vec<ty::Id>,
+        ret: Option<ty::Id>,
+        span: Span,
+    ) -> ty::Id {
+        let kind = ty::Kind::Function { params, ret };
+        let node = ty::Node { kind, span };
+        self.push_type(node)
+    }
+
+    pub(crate) fn make_tuple_type(&mut self, elements: Vec<ty::Id>, span: Span) -> ty::Id {
+        let kind = ty::Kind::Tuple(elements);
+        let node = ty::Node { kind, span };
+        self.push_type(node)
+    }
+}
+
+impl Arena {
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    fn push_pattern(&mut self, node: pattern::Node) -> pattern::Id {
+        let uid = pattern::Id(self.pattern_count());
+        self.patterns.push(node);
+        uid
+    }
+
+    pub(crate) fn make_identifier_pattern(&mut self, name: String, span: Span) -> pattern::Id {
+        let kind = pattern::Kind::Identifier(name);
+        let node = pattern::Node { kind, span };
+        self.push_pattern(node)
+    }
+
+    pub(crate) fn make_wildcard_pattern(&mut self, span: Span) -> pattern::Id {
+        let kind = pattern::Kind::Wildcard;
+        let node = pattern::Node { kind, span };
+        self.push_pattern(node)
+    }
+
+    pub(crate) fn make_error_pattern(&mut self, span: Span) -> pattern::Id {
+        let kind = pattern::Kind::Error;
+        let node = pattern::Node { kind, span };
+        self.push_pattern(node)
+    }
+
+    pub(crate) fn make_literal_pattern(
+        &mut self,
+        literal: expression::Id,
+        span: Span,
+    ) -> pattern::Id {
+        let kind = pattern::Kind::Literal(literal);
+        let node = pattern::Node { kind, span };
+        self.push_pattern(node)
+    }
+
+    pub(crate) fn make_variant_pattern(
+        &mut self,
+        enum_name: String,
+        variant: String,
+        fields: Vec<pattern::Id>,
+        span: Span,
+    ) -> pattern::Id {
+        let kind = pattern::Kind::Variant {
+            enum_name,
+            variant,
+            fields,
+        };
+        let node = pattern::Node { kind, span };
+        self.push_pattern(node)
+    }
+
+    pub(crate) fn make_tuple_pattern(
+        &mut self,
+        elements: Vec<pattern::Id>,
+        span: Span,
+    ) -> pattern::Id {
+        let kind = pattern::Kind::Tuple(elements);
+        let node = pattern::Node { kind, span };
+        self.push_pattern(node)
+    }
+}
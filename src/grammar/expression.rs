@@ -9,7 +9,47 @@ pub(crate) enum Kind {
     Uint64(u64),
     Float64(f64),
     Bool(bool),
+    /// String literal with escapes resolved
+    Str(String),
     Unit,
+    Identifier(String),
+
+    /// `(a, b)` or `(a,)`, `()` is `Unit`
+    Tuple(Vec<Id>),
+
+    /// `tuple.0`
+    TupleIndex {
+        tuple: Id,
+        index: usize,
+    },
+
+    /// `Name { field: value, .. }`, fields in source order
+    StructLiteral {
+        name: String,
+        fields: Vec<(String, Id)>,
+    },
+
+    /// `object.field`
+    Field {
+        object: Id,
+        name: String,
+    },
+
+    /// `Enum::Variant(args)`, the parentheses are omitted for variants without payload
+    Variant {
+        enum_name: String,
+        variant: String,
+        args: Vec<Id>,
+    },
+
+    /// `[a, b, c]`
+    Array(Vec<Id>),
+
+    /// `array[index]`
+    Index {
+        array: Id,
+        index: Id,
+    },
 
     Infix {
         lhs: Id,
@@ -21,11 +61,75 @@ pub(crate) enum Kind {
         exp: Id,
         op: operator::Prefix,
     },
+
+    /// `exp as T`, numeric conversion
+    Cast {
+        exp: Id,
+        ty: ty::Id,
+    },
+
+    /// `{ stmts; tail }`, evaluates to `tail` or `()` without it
+    Block {
+        stmts: Vec<statement::Id>,
+        tail: Option<Id>,
+    },
+
+    /// `if cond { .. } else { .. }`, `otherwise` is a block or another `if`
+    If {
+        cond: Id,
+        then: Id,
+        otherwise: Option<Id>,
+    },
+
+    /// `'label: while cond { .. }`, evaluates to `()`
+    While {
+        label: Option<String>,
+        cond: Id,
+        body: Id,
+    },
+
+    /// `match scrutinee { pattern => body, .. }`, arms are tried in order
+    Match {
+        scrutinee: Id,
+        arms: Vec<Arm>,
+    },
+
+    Break {
+        label: Option<String>,
+    },
+
+    Continue {
+        label: Option<String>,
+    },
+
+    Call {
+        callee: Id,
+        args: Vec<Id>,
+    },
+
+    /// `|a: T, b: U| -> R { body }`, without `-> R` the body is any expression
+    Lambda {
+        params: Vec<item::Param>,
+        ret: Option<ty::Id>,
+        body: Id,
+    },
+
+    /// `return value`, missing value means `()`
+    Return(Option<Id>),
+
+    /// Placeholder for an expression with a syntax error, which is already reported
+    Error,
+}
+
+/// `pattern => body` in a `match`
+#[derive(Debug)]
+pub(crate) struct Arm {
+    pub(crate) pattern: pattern::Id,
+    pub(crate) body: Id,
 }
 
 #[derive(Debug)]
 pub(crate) struct Node {
-    // line
-    // byte
     pub(crate) kind: Kind,
+    pub(crate) span: Span,
 }
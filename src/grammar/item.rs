@@ -0,0 +1,57 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) struct Id(pub(super) usize);
+
+#[derive(Debug)]
+pub(crate) struct Param {
+    pub(crate) name: String,
+    pub(crate) ty: ty::Id,
+}
+
+/// `name: T` in a struct declaration
+#[derive(Debug)]
+pub(crate) struct Field {
+    pub(crate) name: String,
+    pub(crate) ty: ty::Id,
+}
+
+/// `Name(T, U)` in an enum declaration, `Name` alone has no payload
+#[derive(Debug)]
+pub(crate) struct Variant {
+    pub(crate) name: String,
+    pub(crate) fields: Vec<ty::Id>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Kind {
+    /// `fn name(params) -> ret { body }`, missing `ret` means `()`
+    Function {
+        name: String,
+        params: Vec<Param>,
+        ret: Option<ty::Id>,
+        body: expression::Id,
+    },
+
+    /// `const NAME: T = init;`, folded at compile time
+    Const {
+        name: String,
+        ty: ty::Id,
+        init: expression::Id,
+    },
+
+    /// `struct Name { fields }`
+    Struct { name: String, fields: Vec<Field> },
+
+    /// `enum Name { variants }`, variants are tagged by declaration order
+    Enum {
+        name: String,
+        variants: Vec<Variant>,
+    },
+}
+
+#[derive(Debug)]
+pub(crate) struct Node {
+    pub(crate) kind: Kind,
+    pub(crate) span: Span,
+}
@@ -1,9 +1,14 @@
 pub mod expression;
+pub mod item;
 pub mod operator;
+pub mod pattern;
 pub mod statement;
+pub mod ty;
 
 mod arena;
+mod span;
 mod syntax;
 
 pub use arena::Arena;
+pub use span::{SourceFile, Span};
 pub use syntax::Syntax;
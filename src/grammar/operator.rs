@@ -8,6 +8,16 @@ pub(crate) enum Infix {
     Minus,
     Mul,
     Div,
+    Rem,
+
+    /// `+%`, `-%` and `*%` wrap around at the bounds of the type
+    WrappingPlus,
+    WrappingMinus,
+    WrappingMul,
+    /// `+|`, `-|` and `*|` clamp to the bounds of the type
+    SaturatingPlus,
+    SaturatingMinus,
+    SaturatingMul,
 
     Equal,
     NotEqual,
@@ -15,6 +25,22 @@ pub(crate) enum Infix {
     LessEqual,
     Greater,
     GreaterEqual,
+
+    And,
+    Or,
+
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+
+    Assign,
+    PlusAssign,
+    MinusAssign,
+    MulAssign,
+    DivAssign,
+    RemAssign,
 }
 
 impl Infix {
@@ -24,6 +50,14 @@ impl Infix {
             Kind::Minus => Infix::Minus,
             Kind::Star => Infix::Mul,
             Kind::Slash => Infix::Div,
+            Kind::Percent => Infix::Rem,
+
+            Kind::PlusPercent => Infix::WrappingPlus,
+            Kind::MinusPercent => Infix::WrappingMinus,
+            Kind::StarPercent => Infix::WrappingMul,
+            Kind::PlusPipe => Infix::SaturatingPlus,
+            Kind::MinusPipe => Infix::SaturatingMinus,
+            Kind::StarPipe => Infix::SaturatingMul,
 
             Kind::EqualEqual => Infix::Equal,
             Kind::BangEqual => Infix::NotEqual,
@@ -32,6 +66,22 @@ impl Infix {
             Kind::Greater => Infix::Greater,
             Kind::GreaterEqual => Infix::GreaterEqual,
 
+            Kind::AndAnd => Infix::And,
+            Kind::OrOr => Infix::Or,
+
+            Kind::Amp => Infix::BitAnd,
+            Kind::Pipe => Infix::BitOr,
+            Kind::Caret => Infix::BitXor,
+            Kind::LessLess => Infix::Shl,
+            Kind::GreaterGreater => Infix::Shr,
+
+            Kind::Equal => Infix::Assign,
+            Kind::PlusEqual => Infix::PlusAssign,
+            Kind::MinusEqual => Infix::MinusAssign,
+            Kind::StarEqual => Infix::MulAssign,
+            Kind::SlashEqual => Infix::DivAssign,
+            Kind::PercentEqual => Infix::RemAssign,
+
             _ => return None,
         };
 
@@ -42,9 +92,89 @@ impl Infix {
         use Infix::*;
 
         match &self {
-            Equal | NotEqual | Less | LessEqual | Greater | GreaterEqual => (1, 2),
-            Plus | Minus => (3, 4),
-            Mul | Div => (5, 6),
+            // right associative
+            Assign | PlusAssign | MinusAssign | MulAssign | DivAssign | RemAssign => (2, 1),
+            Or => (3, 4),
+            And => (5, 6),
+            Equal | NotEqual | Less | LessEqual | Greater | GreaterEqual => (7, 8),
+            BitOr => (9, 10),
+            BitXor => (11, 12),
+            BitAnd => (13, 14),
+            Shl | Shr => (15, 16),
+            Plus | Minus | WrappingPlus | WrappingMinus | SaturatingPlus | SaturatingMinus => {
+                (17, 18)
+            }
+            Mul | Div | Rem | WrappingMul | SaturatingMul => (19, 20),
+        }
+    }
+
+    /// Operators evaluating the right operand only when needed
+    pub(crate) fn is_lazy(&self) -> bool {
+        matches!(self, Infix::And | Infix::Or)
+    }
+
+    pub(crate) fn is_assignment(&self) -> bool {
+        use Infix::*;
+
+        matches!(
+            self,
+            Assign | PlusAssign | MinusAssign | MulAssign | DivAssign | RemAssign
+        )
+    }
+
+    /// Arithmetic operation performed by a compound assignment
+    pub(crate) fn compound_op(&self) -> Option<Infix> {
+        use Infix::*;
+
+        let op = match self {
+            PlusAssign => Plus,
+            MinusAssign => Minus,
+            MulAssign => Mul,
+            DivAssign => Div,
+            RemAssign => Rem,
+            _ => return None,
+        };
+
+        Some(op)
+    }
+}
+
+impl Infix {
+    /// Operator as written in source
+    pub(crate) fn symbol(&self) -> &'static str {
+        use Infix::*;
+
+        match self {
+            Plus => "+",
+            Minus => "-",
+            Mul => "*",
+            Div => "/",
+            Rem => "%",
+            WrappingPlus => "+%",
+            WrappingMinus => "-%",
+            WrappingMul => "*%",
+            SaturatingPlus => "+|",
+            SaturatingMinus => "-|",
+            SaturatingMul => "*|",
+            Equal => "==",
+            NotEqual => "!=",
+            Less => "<",
+            LessEqual => "<=",
+            Greater => ">",
+            GreaterEqual => ">=",
+            And => "&&",
+            Or => "||",
+            BitAnd => "&",
+            BitOr => "|",
+            BitXor => "^",
+            Shl => "<<",
+            Shr => ">>",
+            Assign => "=",
+            PlusAssign => "+=",
+            MinusAssign => "-=",
+            MulAssign => "*=",
+            DivAssign => "/=",
+            RemAssign => "%=",
         }
     }
 }
@@ -58,12 +188,32 @@ impl Display for Infix {
             Minus => "- Subtraction",
             Mul => "* Multiplication",
             Div => "/ Division",
+            Rem => "% Remainder",
+            WrappingPlus => "+% Wrapping Addition",
+            WrappingMinus => "-% Wrapping Subtraction",
+            WrappingMul => "*% Wrapping Multiplication",
+            SaturatingPlus => "+| Saturating Addition",
+            SaturatingMinus => "-| Saturating Subtraction",
+            SaturatingMul => "*| Saturating Multiplication",
             Equal => "== Equal Comparison",
             NotEqual => "!= NotEqual Comparison",
             Less => "< Less Comparison",
             LessEqual => "<= LessEqual Comparison",
             Greater => "> Greater Comparison",
             GreaterEqual => ">= GreaterEqual Comparison",
+            And => "&& Lazy And",
+            Or => "|| Lazy Or",
+            BitAnd => "& Bitwise And",
+            BitOr => "| Bitwise Or",
+            BitXor => "^ Bitwise Xor",
+            Shl => "<< Shift Left",
+            Shr => ">> Shift Right",
+            Assign => "= Assignment",
+            PlusAssign => "+= Addition Assignment",
+            MinusAssign => "-= Subtraction Assignment",
+            MulAssign => "*= Multiplication Assignment",
+            DivAssign => "/= Division Assignment",
+            RemAssign => "%= Remainder Assignment",
         };
 
         f.write_str(label)
@@ -75,6 +225,8 @@ pub(crate) enum Prefix {
     Plus,
     Minus,
     Negate,
+    /// `~`, flips every bit of an integer
+    BitNot,
 }
 
 impl Prefix {
@@ -83,6 +235,7 @@ impl Prefix {
             Kind::Plus => Prefix::Plus,
             Kind::Minus => Prefix::Minus,
             Kind::Bang => Prefix::Negate,
+            Kind::Tilde => Prefix::BitNot,
             _ => return None,
         };
 
@@ -90,7 +243,21 @@ impl Prefix {
     }
 
     pub(crate) fn get_bp(&self) -> u8 {
-        7
+        22
+    }
+}
+
+impl Prefix {
+    /// Operator as written in source
+    pub(crate) fn symbol(&self) -> &'static str {
+        use Prefix::*;
+
+        match self {
+            Plus => "+",
+            Minus => "-",
+            Negate => "!",
+            BitNot => "~",
+        }
     }
 }
 
@@ -102,25 +269,41 @@ impl Display for Prefix {
             Plus => "+ Unary Plus",
             Minus => "- Unary Minus",
             Negate => "! Negation",
+            BitNot => "~ Bitwise Not",
         };
 
         f.write_str(label)
     }
 }
 
-/// There is no Postfix right now
+/// Postfix operators carry their operands, the parser builds the nodes
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub(crate) enum Postfix {
-    _Nothing,
+    Call,
+    Field,
+    Index,
+    /// `as T`, binds looser than prefix operators and tighter than infix ones
+    Cast,
 }
 
 impl Postfix {
-    pub(crate) fn get(_kind: Kind) -> Option<Postfix> {
-        // unimplemented
-        None
+    pub(crate) fn get(kind: Kind) -> Option<Postfix> {
+        let op = match kind {
+            Kind::LParen => Postfix::Call,
+            Kind::Dot => Postfix::Field,
+            Kind::LBracket => Postfix::Index,
+            Kind::As => Postfix::Cast,
+            _ => return None,
+        };
+
+        Some(op)
     }
 
     pub(crate) fn get_bp(&self) -> u8 {
-        u8::MAX
+        match self {
+            // `-x as u64` casts `-x`, `a * b as f64` casts `b`
+            Postfix::Cast => 21,
+            Postfix::Call | Postfix::Field | Postfix::Index => u8::MAX,
+        }
     }
 }
@@ -0,0 +1,31 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) struct Id(pub(super) usize);
+
+/// Pattern of a `let` binding or a `match` arm, `let` only accepts irrefutable ones
+#[derive(Debug)]
+pub(crate) enum Kind {
+    /// binds the whole value to a name
+    Identifier(String),
+    /// `_`, matches anything without binding
+    Wildcard,
+    /// number, bool or string literal, compared with `==`
+    Literal(expression::Id),
+    /// `(a, (b, c))`, `()` matches unit, parenthesized patterns are elided in parsing
+    Tuple(Vec<Id>),
+    /// `Enum::Variant(a, b)`, the parentheses are omitted for variants without payload
+    Variant {
+        enum_name: String,
+        variant: String,
+        fields: Vec<Id>,
+    },
+    /// Placeholder for a pattern with a syntax error, which is already reported
+    Error,
+}
+
+#[derive(Debug)]
+pub(crate) struct Node {
+    pub(crate) kind: Kind,
+    pub(crate) span: Span,
+}
@@ -6,13 +6,16 @@ pub(crate) struct Id(pub(super) usize);
 #[derive(Debug)]
 pub(crate) enum Kind {
     Expression(expression::Id),
-    Block(Vec<Id>),
-    Let { name: String, init: expression::Id },
+    /// `let pattern: ty = init;` with optional annotation
+    Let {
+        pattern: pattern::Id,
+        ty: Option<ty::Id>,
+        init: expression::Id,
+    },
 }
 
 #[derive(Debug)]
 pub(crate) struct Node {
-    // line
-    // byte
     pub(crate) kind: Kind,
+    pub(crate) span: Span,
 }
@@ -3,4 +3,17 @@ use super::*;
 #[derive(Debug, Default)]
 pub struct Syntax {
     pub(crate) arena: arena::Arena,
+    /// Sources the nodes were parsed from, indexed by `Span.file`
+    pub(crate) files: Vec<SourceFile>,
+}
+
+impl Syntax {
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// 1-based line where the span starts
+    pub fn line(&self, span: Span) -> usize {
+        self.files[span.file].line(span.start)
+    }
 }
@@ -0,0 +1,27 @@
+use super::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) struct Id(pub(super) usize);
+
+/// Type expression as written in annotations
+#[derive(Debug)]
+pub(crate) enum Kind {
+    /// `i64`, `bool`, ...
+    Named(String),
+    /// `!`
+    Never,
+    /// `()`, `(T,)`, `(T, U)`, parenthesized types are elided in parsing
+    Tuple(Vec<Id>),
+    /// `[T]`
+    Array(Id),
+    /// `fn(T, U) -> R`, missing `ret` means `()`
+    Function { params: Vec<Id>, ret: Option<Id> },
+    /// Placeholder for a type with a syntax error, which is already reported
+    Error,
+}
+
+#[derive(Debug)]
+pub(crate) struct Node {
+    pub(crate) kind: Kind,
+    pub(crate) span: Span,
+}
@@ -1,7 +1,20 @@
 use thiserror::Error;
 
+use crate::diagnostic::Diagnostic;
+use crate::grammar::Span;
+
 #[derive(Debug, Clone, PartialEq, Error)]
-#[error("lexing error")]
-pub struct Error {}
+#[error("{message}")]
+pub struct Error {
+    pub message: &'static str,
+    /// Text of the token being scanned when it failed
+    pub span: Span,
+}
+
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        Diagnostic::error(error.message).with_primary(error.span, "")
+    }
+}
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,16 +1,20 @@
 use super::*;
+use crate::grammar::Span;
 use token::Kind;
 use token::Token;
 
 #[derive(Debug)]
 pub struct Lexer<'s> {
     source: &'s str,
+    /// 1-based line of `current_byte`
     line: usize,
+    /// Line where the token being scanned starts
+    start_line: usize,
     start: usize,
     current_byte: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum NumberPrefix {
     Bin,  // "0b"
     Oct,  // "0o"
@@ -41,12 +45,17 @@ impl<'s> Lexer<'s> {
     pub fn new(source: &'s str) -> Self {
         Self {
             source,
-            line: 0,
+            line: 1,
+            start_line: 1,
             start: 0,
             current_byte: 0,
         }
     }
 
+    pub fn source(&self) -> &'s str {
+        self.source
+    }
+
     fn advance(&mut self) -> Option<char> {
         if self.is_at_end() {
             return None;
@@ -74,12 +83,7 @@ impl<'s> Lexer<'s> {
     fn skip_whitespace(&mut self) {
         while let Some(ch) = self.peek() {
             match ch {
-                ' ' | '\r' | '\t' => {
-                    self.advance();
-                }
-
-                '\n' => {
-                    self.line += 1;
+                ' ' | '\r' | '\t' | '\n' => {
                     self.advance();
                 }
 
@@ -119,6 +123,7 @@ impl<'s> Lexer<'s> {
     pub fn scan_token(&mut self) -> Result<Token<'s>> {
         self.skip_whitespace();
         self.start = self.current_byte;
+        self.start_line = self.line;
 
         if self.is_at_end() {
             return Ok(self.make_token(Kind::EOF));
@@ -131,14 +136,29 @@ impl<'s> Lexer<'s> {
             ')' => self.make_token(Kind::RParen),
             '{' => self.make_token(Kind::LBrace),
             '}' => self.make_token(Kind::RBrace),
+            '[' => self.make_token(Kind::LBracket),
+            ']' => self.make_token(Kind::RBracket),
             ';' => self.make_token(Kind::Semicolon),
             ',' => self.make_token(Kind::Comma),
-            ':' => self.make_token(Kind::Colon),
+            ':' => {
+                if self.match_token(':') {
+                    self.make_token(Kind::ColonColon)
+                } else {
+                    self.make_token(Kind::Colon)
+                }
+            }
             '.' => self.make_token(Kind::Dot),
+            '\'' => self.scan_label(),
+            '"' => self.scan_string()?,
+            'r' if matches!(self.peek(), Some('"' | '#')) => self.scan_raw_string()?,
 
             '+' => {
                 if self.match_token('=') {
                     self.make_token(Kind::PlusEqual)
+                } else if self.match_token('%') {
+                    self.make_token(Kind::PlusPercent)
+                } else if self.match_token('|') {
+                    self.make_token(Kind::PlusPipe)
                 } else {
                     self.make_token(Kind::Plus)
                 }
@@ -146,6 +166,10 @@ impl<'s> Lexer<'s> {
             '-' => {
                 if self.match_token('=') {
                     self.make_token(Kind::MinusEqual)
+                } else if self.match_token('%') {
+                    self.make_token(Kind::MinusPercent)
+                } else if self.match_token('|') {
+                    self.make_token(Kind::MinusPipe)
                 } else if self.match_token('>') {
                     self.make_token(Kind::Arrow)
                 } else {
@@ -155,6 +179,10 @@ impl<'s> Lexer<'s> {
             '*' => {
                 if self.match_token('=') {
                     self.make_token(Kind::StarEqual)
+                } else if self.match_token('%') {
+                    self.make_token(Kind::StarPercent)
+                } else if self.match_token('|') {
+                    self.make_token(Kind::StarPipe)
                 } else {
                     self.make_token(Kind::Star)
                 }
@@ -185,6 +213,8 @@ impl<'s> Lexer<'s> {
             '=' => {
                 if self.match_token('=') {
                     self.make_token(Kind::EqualEqual)
+                } else if self.match_token('>') {
+                    self.make_token(Kind::FatArrow)
                 } else {
                     self.make_token(Kind::Equal)
                 }
@@ -192,6 +222,8 @@ impl<'s> Lexer<'s> {
             '<' => {
                 if self.match_token('=') {
                     self.make_token(Kind::LessEqual)
+                } else if self.match_token('<') {
+                    self.make_token(Kind::LessLess)
                 } else {
                     self.make_token(Kind::Less)
                 }
@@ -199,6 +231,8 @@ impl<'s> Lexer<'s> {
             '>' => {
                 if self.match_token('=') {
                     self.make_token(Kind::GreaterEqual)
+                } else if self.match_token('>') {
+                    self.make_token(Kind::GreaterGreater)
                 } else {
                     self.make_token(Kind::Greater)
                 }
@@ -207,33 +241,33 @@ impl<'s> Lexer<'s> {
                 if self.match_token('&') {
                     self.make_token(Kind::AndAnd)
                 } else {
-                    return Err(self.make_error("Impossible &"));
+                    self.make_token(Kind::Amp)
                 }
             }
+            '^' => self.make_token(Kind::Caret),
+            '~' => self.make_token(Kind::Tilde),
             '|' => {
                 if self.match_token('|') {
                     self.make_token(Kind::OrOr)
                 } else {
-                    return Err(self.make_error("Impossible |"));
+                    self.make_token(Kind::Pipe)
                 }
             }
 
-            c => {
-                if c.is_ascii_digit() {
-                    self.current_byte -= 1;
-                    self.scan_numeric()
-                } else {
-                    self.scan_ident_or_keyword()
-                }
+            c if c.is_ascii_digit() => {
+                self.current_byte -= 1;
+                self.scan_numeric()
             }
+            c if c.is_alphabetic() || c == '_' => self.scan_ident_or_keyword(),
+            _ => return Err(self.make_error("Unexpected character")),
         };
 
         Ok(result)
     }
 
+    /// Comment text up to the end of the line, the newline is left as whitespace
     fn scan_comment(&mut self) -> Token<'s> {
-        let line = self.line;
-        while line == self.line {
+        while !matches!(self.peek(), None | Some('\n')) {
             self.advance();
         }
         self.make_token(Kind::LineComment)
@@ -281,13 +315,13 @@ impl<'s> Lexer<'s> {
     }
 
     fn scan_numeric(&mut self) -> Token<'s> {
-        let radix = dbg!(self.scan_number_prefix()).to_num();
-        let has_dot = dbg!(self.scan_number_core());
-        let mut num = dbg!(self.get_text());
+        let radix = self.scan_number_prefix().to_num();
+        let has_dot = self.scan_number_core();
+        let mut num = self.get_text();
         if radix != 10 {
             num = &num[2..];
         }
-        let post = dbg!(self.scan_number_postfix());
+        let post = self.scan_number_postfix();
         let kind = match post {
             NumberPostfix::Int64 => Kind::Int64Lit(i64::from_str_radix(num, radix).unwrap()),
             NumberPostfix::Uint64 => Kind::Uint64Lit(u64::from_str_radix(num, radix).unwrap()),
@@ -301,14 +335,85 @@ impl<'s> Lexer<'s> {
         self.make_token(kind)
     }
 
-    fn scan_ident_or_keyword(&mut self) -> Token<'s> {
-        while self.peek().unwrap().is_alphanumeric() {
+    fn skip_ident(&mut self) {
+        while let Some(ch) = self.peek()
+            && (ch.is_alphanumeric() || ch == '_')
+        {
             self.advance();
         }
+    }
+
+    fn scan_ident_or_keyword(&mut self) -> Token<'s> {
+        self.skip_ident();
         let text = self.get_text();
         self.make_token(Kind::ident_or_keyword(text))
     }
 
+    /// String literal after the opening quote, escapes are checked but kept
+    fn scan_string(&mut self) -> Result<Token<'s>> {
+        // the literal is scanned to its end even after a bad escape, so the
+        // rest of it is not lexed as code
+        let mut unknown_escape = None;
+        loop {
+            match self.advance() {
+                Some('"') => break,
+                Some('\\') => {
+                    let at = self.current_byte - 1;
+                    match self.advance() {
+                        Some('n' | 't' | 'r' | '0' | '\\' | '"' | '\'') => (),
+                        Some(_) => {
+                            unknown_escape.get_or_insert(Span::new(0, at, self.current_byte));
+                        }
+                        None => return Err(self.make_error("Unterminated string")),
+                    }
+                }
+                Some(_) => (),
+                None => return Err(self.make_error("Unterminated string")),
+            }
+        }
+
+        match unknown_escape {
+            Some(span) => Err(Error {
+                message: "Unknown escape sequence",
+                span,
+            }),
+            None => Ok(self.make_token(Kind::StringLit)),
+        }
+    }
+
+    /// Raw string after `r`: `r"text"` or `r#"text"#`, closed by a quote with as many `#`
+    fn scan_raw_string(&mut self) -> Result<Token<'s>> {
+        let mut hashes = 0;
+        while self.match_token('#') {
+            hashes += 1;
+        }
+        if !self.match_token('"') {
+            return Err(self.make_error("Expected `\"` in raw string"));
+        }
+
+        loop {
+            match self.advance() {
+                Some('"') => {
+                    let rest = &self.source.as_bytes()[self.current_byte..];
+                    if rest.len() >= hashes && rest[..hashes].iter().all(|b| *b == b'#') {
+                        self.current_byte += hashes;
+                        break;
+                    }
+                }
+                Some(_) => (),
+                None => return Err(self.make_error("Unterminated raw string")),
+            }
+        }
+
+        Ok(self.make_token(Kind::RawStringLit))
+    }
+
+    /// Loop label, the token text keeps the leading `'`
+    fn scan_label(&mut self) -> Token<'s> {
+        self.skip_ident();
+        self.make_token(Kind::Label)
+    }
+
     fn get_text(&self) -> &'s str {
         &self.source[self.start..self.current_byte]
     }
@@ -317,34 +422,40 @@ impl<'s> Lexer<'s> {
         Token {
             kind,
             text: self.get_text(),
-            line: self.line,
+            line: self.start_line,
             byte: self.start,
         }
     }
 
-    fn make_error(&self, message: &str) -> Error {
-        eprintln!("{message}");
-        Error {}
+    fn make_error(&self, message: &'static str) -> Error {
+        Error {
+            message,
+            span: Span::new(0, self.start, self.current_byte),
+        }
     }
 }
 
+#[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
     fn simple_tokens() -> Result<()> {
         let mut lex = Lexer::new(
-            "()     {}, :;.
+            "()     {}[], :;.
             + += - -= *
             *= / /= % %=
         ! != = == < <= > >=
-                    && ||",
+                    && || << >> & | ^ ~
+            +% -% *% +| -| *|",
         );
 
         assert_eq!(lex.scan_token()?.kind, Kind::LParen);
         assert_eq!(lex.scan_token()?.kind, Kind::RParen);
         assert_eq!(lex.scan_token()?.kind, Kind::LBrace);
         assert_eq!(lex.scan_token()?.kind, Kind::RBrace);
+        assert_eq!(lex.scan_token()?.kind, Kind::LBracket);
+        assert_eq!(lex.scan_token()?.kind, Kind::RBracket);
         assert_eq!(lex.scan_token()?.kind, Kind::Comma);
         assert_eq!(lex.scan_token()?.kind, Kind::Colon);
         assert_eq!(lex.scan_token()?.kind, Kind::Semicolon);
@@ -369,6 +480,108 @@ mod test {
         assert_eq!(lex.scan_token()?.kind, Kind::GreaterEqual);
         assert_eq!(lex.scan_token()?.kind, Kind::AndAnd);
         assert_eq!(lex.scan_token()?.kind, Kind::OrOr);
+        assert_eq!(lex.scan_token()?.kind, Kind::LessLess);
+        assert_eq!(lex.scan_token()?.kind, Kind::GreaterGreater);
+        assert_eq!(lex.scan_token()?.kind, Kind::Amp);
+        assert_eq!(lex.scan_token()?.kind, Kind::Pipe);
+        assert_eq!(lex.scan_token()?.kind, Kind::Caret);
+        assert_eq!(lex.scan_token()?.kind, Kind::Tilde);
+        assert_eq!(lex.scan_token()?.kind, Kind::PlusPercent);
+        assert_eq!(lex.scan_token()?.kind, Kind::MinusPercent);
+        assert_eq!(lex.scan_token()?.kind, Kind::StarPercent);
+        assert_eq!(lex.scan_token()?.kind, Kind::PlusPipe);
+        assert_eq!(lex.scan_token()?.kind, Kind::MinusPipe);
+        assert_eq!(lex.scan_token()?.kind, Kind::StarPipe);
+        assert_eq!(lex.scan_token()?.kind, Kind::EOF);
+
+        Ok(())
+    }
+
+    #[test]
+    fn labels_and_loop_keywords() -> Result<()> {
+        let mut lex = Lexer::new("'outer: while break continue 'outer");
+
+        let label = lex.scan_token()?;
+        assert_eq!(label.kind, Kind::Label);
+        assert_eq!(label.text, "'outer");
+        assert_eq!(lex.scan_token()?.kind, Kind::Colon);
+        assert_eq!(lex.scan_token()?.kind, Kind::While);
+        assert_eq!(lex.scan_token()?.kind, Kind::Break);
+        assert_eq!(lex.scan_token()?.kind, Kind::Continue);
+        assert_eq!(lex.scan_token()?.kind, Kind::Label);
+        assert_eq!(lex.scan_token()?.kind, Kind::EOF);
+
+        Ok(())
+    }
+
+    #[test]
+    fn string_literals() -> Result<()> {
+        let mut lex = Lexer::new(r###"  "a\n\"b\"" r"c\n" r#"say "hi""# rust  "###);
+
+        let string = lex.scan_token()?;
+        assert_eq!(string.kind, Kind::StringLit);
+        assert_eq!(string.string_value(), "a\n\"b\"");
+
+        let raw = lex.scan_token()?;
+        assert_eq!(raw.kind, Kind::RawStringLit);
+        assert_eq!(raw.string_value(), r"c\n");
+
+        let hashed = lex.scan_token()?;
+        assert_eq!(hashed.kind, Kind::RawStringLit);
+        assert_eq!(hashed.string_value(), r#"say "hi""#);
+
+        assert_eq!(lex.scan_token()?.kind, Kind::Identifier);
+        assert_eq!(lex.scan_token()?.kind, Kind::EOF);
+
+        let mut lex = Lexer::new(r#""bad \q" x"#);
+        let error = lex.scan_token().expect_err("`\\q` is not an escape");
+        assert_eq!(error.message, "Unknown escape sequence");
+        assert_eq!((error.span.start, error.span.end), (5, 7));
+        assert_eq!(lex.scan_token()?.kind, Kind::Identifier);
+        assert!(Lexer::new(r#""open"#).scan_token().is_err());
+
+        let mut lex = Lexer::new("a \"open");
+        lex.scan_token()?;
+        let error = lex.scan_token().expect_err("string is not closed");
+        assert_eq!(error.message, "Unterminated string");
+        assert_eq!((error.span.start, error.span.end), (2, 7));
+
+        Ok(())
+    }
+
+    #[test]
+    fn match_tokens() -> Result<()> {
+        let mut lex = Lexer::new("match Shape::Circle(r) => _");
+
+        assert_eq!(lex.scan_token()?.kind, Kind::Match);
+        assert_eq!(lex.scan_token()?.kind, Kind::Identifier);
+        assert_eq!(lex.scan_token()?.kind, Kind::ColonColon);
+        assert_eq!(lex.scan_token()?.kind, Kind::Identifier);
+        assert_eq!(lex.scan_token()?.kind, Kind::LParen);
+        assert_eq!(lex.scan_token()?.kind, Kind::Identifier);
+        assert_eq!(lex.scan_token()?.kind, Kind::RParen);
+        assert_eq!(lex.scan_token()?.kind, Kind::FatArrow);
+        assert_eq!(lex.scan_token()?.kind, Kind::Identifier);
+        assert_eq!(lex.scan_token()?.kind, Kind::EOF);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tokens_know_their_line() -> Result<()> {
+        let mut lex = Lexer::new("a // note\n\n  b \"x\ny\" c");
+
+        assert_eq!(lex.scan_token()?.line, 1);
+        assert_eq!(lex.scan_token()?.kind, Kind::LineComment);
+        assert_eq!(lex.scan_token()?.line, 3);
+        let string = lex.scan_token()?;
+        assert_eq!((string.kind, string.line), (Kind::StringLit, 3));
+        let c = lex.scan_token()?;
+        assert_eq!((c.line, c.byte), (4, 21));
+        assert_eq!(lex.scan_token()?.kind, Kind::EOF);
+
+        let mut lex = Lexer::new("// trailing comment");
+        assert_eq!(lex.scan_token()?.kind, Kind::LineComment);
         assert_eq!(lex.scan_token()?.kind, Kind::EOF);
 
         Ok(())
@@ -1,52 +1,77 @@
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Kind {
     Identifier,
+    Label, // 'name
 
     Function, // fn
     Let,      // let
     Const,    // const
+    Struct,   // struct
+    Enum,     // enum
+    Match,    // match
     Return,   // return
     If,       // if
     Else,     // else
     While,    // while
+    Break,    // break
+    Continue, // continue
     True,     // true
     False,    // false
+    As,       // as
 
     Int64Lit(i64),
     Uint64Lit(u64),
     FloatLit(f64),
+    StringLit,    // "text", escapes are kept in the token text
+    RawStringLit, // r"text" or r#"text"#
 
-    LParen,    // (
-    RParen,    // )
-    LBrace,    // {
-    RBrace,    // }
-    Comma,     // ,
-    Semicolon, // ;
-    Colon,     // ,
-    Dot,       // .
+    LParen,     // (
+    RParen,     // )
+    LBrace,     // {
+    RBrace,     // }
+    LBracket,   // [
+    RBracket,   // ]
+    Comma,      // ,
+    Semicolon,  // ;
+    Colon,      // :
+    ColonColon, // ::
+    Dot,        // .
 
-    Plus,         // +
-    PlusEqual,    // +=
-    Minus,        // -
-    MinusEqual,   // -=
-    Arrow,        // ->
-    Star,         // *
-    StarEqual,    // *=
-    Slash,        // /
-    SlashEqual,   // /=
-    LineComment,  // //
-    Percent,      // %
-    PercentEqual, // %=
-    Bang,         // !
-    BangEqual,    // !=
-    Equal,        // =
-    EqualEqual,   // ==
-    Less,         // <
-    LessEqual,    // <=
-    Greater,      // >
-    GreaterEqual, // >=
-    AndAnd,       // &&
-    OrOr,         // ||
+    Plus,           // +
+    PlusEqual,      // +=
+    PlusPercent,    // +%
+    PlusPipe,       // +|
+    Minus,          // -
+    MinusEqual,     // -=
+    MinusPercent,   // -%
+    MinusPipe,      // -|
+    Arrow,          // ->
+    Star,           // *
+    StarEqual,      // *=
+    StarPercent,    // *%
+    StarPipe,       // *|
+    Slash,          // /
+    SlashEqual,     // /=
+    LineComment,    // //
+    Percent,        // %
+    PercentEqual,   // %=
+    Bang,           // !
+    BangEqual,      // !=
+    Equal,          // =
+    EqualEqual,     // ==
+    FatArrow,       // =>
+    Less,           // <
+    LessEqual,      // <=
+    LessLess,       // <<
+    Greater,        // >
+    GreaterEqual,   // >=
+    GreaterGreater, // >>
+    AndAnd,         // &&
+    OrOr,           // ||
+    Amp,            // &
+    Pipe,           // |
+    Caret,          // ^
+    Tilde,          // ~
 
     EOF,
     Default,
@@ -55,20 +80,104 @@ pub enum Kind {
 impl Kind {
     pub fn ident_or_keyword(source: &str) -> Kind {
         match source {
-            "fn" => Kind::Function,   // fn
-            "let" => Kind::Let,       // let
-            "const" => Kind::Const,   // const
-            "return" => Kind::Return, // return
-            "if" => Kind::If,         // if
-            "else" => Kind::Else,     // else
-            "while" => Kind::While,   // while
-            "true" => Kind::True,     // true
-            "false" => Kind::False,   // false
-            _ => return Kind::Identifier,
+            "fn" => Kind::Function,       // fn
+            "let" => Kind::Let,           // let
+            "const" => Kind::Const,       // const
+            "struct" => Kind::Struct,     // struct
+            "enum" => Kind::Enum,         // enum
+            "match" => Kind::Match,       // match
+            "return" => Kind::Return,     // return
+            "if" => Kind::If,             // if
+            "else" => Kind::Else,         // else
+            "while" => Kind::While,       // while
+            "break" => Kind::Break,       // break
+            "continue" => Kind::Continue, // continue
+            "true" => Kind::True,         // true
+            "false" => Kind::False,       // false
+            "as" => Kind::As,             // as
+            _ => Kind::Identifier,
         }
     }
 }
 
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Kind::Identifier => "identifier",
+            Kind::Label => "label",
+            Kind::Function => "`fn`",
+            Kind::Let => "`let`",
+            Kind::Const => "`const`",
+            Kind::Struct => "`struct`",
+            Kind::Enum => "`enum`",
+            Kind::Match => "`match`",
+            Kind::Return => "`return`",
+            Kind::If => "`if`",
+            Kind::Else => "`else`",
+            Kind::While => "`while`",
+            Kind::Break => "`break`",
+            Kind::Continue => "`continue`",
+            Kind::True => "`true`",
+            Kind::False => "`false`",
+            Kind::As => "`as`",
+            Kind::Int64Lit(_) => "integer literal",
+            Kind::Uint64Lit(_) => "integer literal",
+            Kind::FloatLit(_) => "float literal",
+            Kind::StringLit => "string literal",
+            Kind::RawStringLit => "string literal",
+            Kind::LParen => "`(`",
+            Kind::RParen => "`)`",
+            Kind::LBrace => "`{`",
+            Kind::RBrace => "`}`",
+            Kind::LBracket => "`[`",
+            Kind::RBracket => "`]`",
+            Kind::Comma => "`,`",
+            Kind::Semicolon => "`;`",
+            Kind::Colon => "`:`",
+            Kind::ColonColon => "`::`",
+            Kind::Dot => "`.`",
+            Kind::Plus => "`+`",
+            Kind::PlusEqual => "`+=`",
+            Kind::PlusPercent => "`+%`",
+            Kind::PlusPipe => "`+|`",
+            Kind::Minus => "`-`",
+            Kind::MinusEqual => "`-=`",
+            Kind::MinusPercent => "`-%`",
+            Kind::MinusPipe => "`-|`",
+            Kind::Arrow => "`->`",
+            Kind::Star => "`*`",
+            Kind::StarEqual => "`*=`",
+            Kind::StarPercent => "`*%`",
+            Kind::StarPipe => "`*|`",
+            Kind::Slash => "`/`",
+            Kind::SlashEqual => "`/=`",
+            Kind::LineComment => "comment",
+            Kind::Percent => "`%`",
+            Kind::PercentEqual => "`%=`",
+            Kind::Bang => "`!`",
+            Kind::BangEqual => "`!=`",
+            Kind::Equal => "`=`",
+            Kind::EqualEqual => "`==`",
+            Kind::FatArrow => "`=>`",
+            Kind::Less => "`<`",
+            Kind::LessEqual => "`<=`",
+            Kind::LessLess => "`<<`",
+            Kind::Greater => "`>`",
+            Kind::GreaterEqual => "`>=`",
+            Kind::GreaterGreater => "`>>`",
+            Kind::AndAnd => "`&&`",
+            Kind::OrOr => "`||`",
+            Kind::Amp => "`&`",
+            Kind::Pipe => "`|`",
+            Kind::Caret => "`^`",
+            Kind::Tilde => "`~`",
+            Kind::EOF => "end of file",
+            Kind::Default => "start of file",
+        };
+        write!(f, "{text}")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token<'s> {
     pub(crate) kind: Kind,
@@ -79,10 +188,45 @@ pub struct Token<'s> {
 
 impl<'s> Token<'s> {
     pub fn get_kind(&self) -> Kind {
-        self.kind.clone()
+        self.kind
     }
 
-    pub fn default() -> Self {
+    /// Contents of a string literal without quotes, with escapes resolved
+    ///
+    /// Escapes are validated by the lexer
+    pub fn string_value(&self) -> String {
+        match self.kind {
+            Kind::StringLit => {
+                let mut value = String::new();
+                let mut chars = self.text[1..self.text.len() - 1].chars();
+                while let Some(ch) = chars.next() {
+                    if ch != '\\' {
+                        value.push(ch);
+                        continue;
+                    }
+                    let escaped = match chars.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some(ch) => ch,
+                        None => unreachable!("lexer rejects a trailing backslash"),
+                    };
+                    value.push(escaped);
+                }
+                value
+            }
+            Kind::RawStringLit => {
+                let hashes = self.text[1..].find('"').expect("raw string has quotes");
+                self.text[hashes + 2..self.text.len() - hashes - 1].to_owned()
+            }
+            kind => panic!("not a string literal: {kind:?}"),
+        }
+    }
+}
+
+impl Default for Token<'_> {
+    fn default() -> Self {
         Self {
             kind: Kind::Default,
             text: "",
@@ -1,5 +1,8 @@
+#![allow(clippy::module_inception)]
+
 pub mod bytecode;
 pub mod codegen;
+pub mod diagnostic;
 pub mod grammar;
 pub mod lexer;
 pub mod module;
@@ -1,31 +1,67 @@
+use std::io::IsTerminal;
+use std::process::ExitCode;
+
 use rail::codegen::CodeGen;
+use rail::diagnostic::{Diagnostic, Renderer};
 use rail::lexer::Lexer;
 use rail::parser::Parser;
-use rail::printer::TreePrinter;
 use rail::semantic::TypeEnv;
 use rail::typechecker::Typer;
 use rail::vm::Vm;
 
-fn main() {
-    println!("hello from rail");
-    let args: Vec<String> = std::env::args().into_iter().collect();
-    let file = std::path::PathBuf::from(&args[1]);
-    let source = std::fs::read_to_string(file).unwrap();
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = args.get(1) else {
+        eprintln!("usage: rail <file>");
+        return ExitCode::FAILURE;
+    };
+    let file = std::path::PathBuf::from(path);
+    let source = match std::fs::read_to_string(&file) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("cannot read `{}`: {error}", file.display());
+            return ExitCode::FAILURE;
+        }
+    };
 
     let lexer = Lexer::new(&source);
-    let parser = Parser::new(lexer);
-    let syntax = parser.parse();
+    let parser = Parser::with_name(lexer, &file.to_string_lossy());
+    let (syntax, errors) = parser.parse_with_errors();
+
+    let files = syntax.files().to_vec();
+    let renderer = Renderer::new(&files).with_color(std::io::stderr().is_terminal());
+    let report = |diagnostic: Diagnostic| eprint!("{}", renderer.render(&diagnostic));
 
-    let printer = TreePrinter::new(&syntax);
-    printer.print();
+    if !errors.is_empty() {
+        for error in errors {
+            report(error);
+        }
+        return ExitCode::FAILURE;
+    }
 
     let env = TypeEnv::new();
     let typer = Typer::new(&env);
-    let module = typer.check(syntax).unwrap();
+    let module = match typer.check(syntax) {
+        Ok(module) => module,
+        Err(error) => {
+            report(error.into());
+            return ExitCode::FAILURE;
+        }
+    };
+    for warning in module.warnings() {
+        eprint!("{}", renderer.render(warning));
+    }
 
     let mut compiler = CodeGen::new();
     let program = compiler.compile(module);
 
     let mut vm = Vm::from(&program);
-    let _ = vm.run();
+    match vm.run() {
+        // only the low byte reaches the shell, as with `std::process::exit`
+        Ok(code) => ExitCode::from(code as u8),
+        Err(error) => {
+            report(error.into());
+            ExitCode::FAILURE
+        }
+    }
 }
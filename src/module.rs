@@ -1,10 +1,26 @@
 use std::collections::HashMap;
 
+use crate::diagnostic::Diagnostic;
 use crate::grammar::*;
+use crate::runtime::{Native, Value};
 use crate::semantic::*;
 
 #[derive(Debug, Default)]
 pub struct Module {
     pub(crate) syntax: Syntax,
     pub(crate) types: HashMap<expression::Id, Type>,
+    /// Folded values of global constants
+    pub(crate) consts: HashMap<String, Value>,
+    /// Enclosing locals each lambda captures, in the order they are stored in the closure
+    pub(crate) captures: HashMap<expression::Id, Vec<String>>,
+    /// Host functions the program may call, indexed like `Program.natives`
+    pub(crate) natives: Vec<Native>,
+    /// Problems found while checking that do not stop the program from compiling
+    pub(crate) warnings: Vec<Diagnostic>,
+}
+
+impl Module {
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
 }
@@ -1,7 +1,29 @@
 use thiserror::Error;
 
+use crate::diagnostic::Diagnostic;
+use crate::grammar::Span;
+
 #[derive(Debug, Error)]
-#[error("Parsing error")]
-pub struct Error {}
+#[error("{message}")]
+pub struct Error {
+    pub message: String,
+    /// Token the parser could not make sense of
+    pub span: Span,
+}
+
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        Diagnostic::error(error.message).with_primary(error.span, "")
+    }
+}
+
+impl From<crate::lexer::Error> for Error {
+    fn from(error: crate::lexer::Error) -> Self {
+        Self {
+            message: error.message.to_owned(),
+            span: error.span,
+        }
+    }
+}
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,4 +1,5 @@
 use crate::{
+    diagnostic::Diagnostic,
     grammar::*,
     lexer::Lexer,
     lexer::{token, token::Token},
@@ -9,93 +10,726 @@ pub struct Parser<'s> {
     arena: Arena,
     previous: Token<'s>,
     current: Token<'s>,
+    /// Struct literals are not allowed where `{` starts a block, like `if x {`
+    struct_literals: bool,
+    /// Parsed source, the only file spans refer to
+    file: SourceFile,
+    errors: Vec<Diagnostic>,
+    /// Set by a syntax error until the next synchronization point, errors are
+    /// not reported meanwhile as they are most likely caused by the first one
+    panicking: bool,
 }
 
 impl<'s> Parser<'s> {
     pub fn new(lexer: Lexer<'s>) -> Self {
+        Self::with_name(lexer, "<input>")
+    }
+
+    /// Parser of a source named like the file it was read from
+    pub fn with_name(lexer: Lexer<'s>, name: &str) -> Self {
+        let file = SourceFile::new(name, lexer.source());
         Self {
             lexer,
             arena: Arena::default(),
             previous: Token::default(),
             current: Token::default(),
+            struct_literals: true,
+            file,
+            errors: Vec::new(),
+            panicking: false,
         }
     }
 
+    /// Span from `start` to the end of the previous token
+    fn span_from(&self, start: usize) -> Span {
+        let end = self.previous.byte + self.previous.text.len();
+        Span::new(0, start, end.max(start))
+    }
+
+    /// Span of the text of a token
+    fn token_span(token: &Token) -> Span {
+        Span::new(0, token.byte, token.byte + token.text.len())
+    }
+
     fn advance(&mut self) {
-        let token = self.lexer.scan_token().unwrap();
-        dbg!(&token);
+        let token = loop {
+            match self.lexer.scan_token() {
+                Ok(token) if token.get_kind() == token::Kind::LineComment => (),
+                Ok(token) => break token,
+                // the bad text is skipped, errors it causes in parsing are not reported
+                Err(error) => {
+                    self.errors.push(error.into());
+                    self.panicking = true;
+                }
+            }
+        };
         self.previous = std::mem::replace(&mut self.current, token);
     }
 
     fn consume(&mut self, kind: token::Kind) {
-        dbg!(kind);
         if self.current.get_kind() == kind {
             self.advance();
+        } else if kind == token::Kind::Semicolon {
+            // a missing `;` belongs right after the statement, not at whatever follows it
+            let end = self.previous.byte + self.previous.text.len();
+            let current = self.current.clone();
+            self.expected_at(&kind.to_string(), &current, Span::new(0, end, end));
         } else {
-            panic!("not consumed: {kind:?}");
+            let current = self.current.clone();
+            self.expected(&kind.to_string(), &current);
         }
     }
 
-    pub fn parse(mut self) -> Syntax {
+    /// Report that `what` should be where the token is
+    fn expected(&mut self, what: &str, token: &Token) {
+        self.expected_at(what, token, Self::token_span(token));
+    }
+
+    /// Report that `what` should be at `span`, instead of the token found there
+    fn expected_at(&mut self, what: &str, token: &Token, span: Span) {
+        if self.panicking {
+            return;
+        }
+        self.panicking = true;
+
+        let found = match token.get_kind() {
+            token::Kind::EOF => "end of file".to_owned(),
+            _ => format!("`{}`", token.text),
+        };
+        let diagnostic = Diagnostic::error(format!("expected {what}, found {found}"))
+            .with_primary(span, format!("expected {what}"));
+        self.errors.push(diagnostic);
+    }
+
+    /// Skip past the end of the broken statement, up to where the next one can start
+    fn synchronize(&mut self) {
+        self.panicking = false;
+        if self.previous.get_kind() == token::Kind::Semicolon {
+            return;
+        }
+        loop {
+            match self.current.get_kind() {
+                token::Kind::Semicolon => return self.advance(),
+                token::Kind::EOF
+                | token::Kind::RBrace
+                | token::Kind::Let
+                | token::Kind::Function
+                | token::Kind::Const
+                | token::Kind::Struct
+                | token::Kind::Enum => return,
+                _ => self.advance(),
+            }
+        }
+    }
+
+    /// Skip the rest of a broken item, up to where the next one can start
+    fn synchronize_item(&mut self) {
+        self.panicking = false;
+        while !matches!(
+            self.current.get_kind(),
+            token::Kind::EOF
+                | token::Kind::Function
+                | token::Kind::Const
+                | token::Kind::Struct
+                | token::Kind::Enum
+        ) {
+            self.advance();
+        }
+    }
+
+    /// Parse the whole source, failing with every syntax error found in it
+    pub fn parse(self) -> Result<Syntax, Vec<Diagnostic>> {
+        let (syntax, errors) = self.parse_with_errors();
+        match errors.is_empty() {
+            true => Ok(syntax),
+            false => Err(errors),
+        }
+    }
+
+    /// Parse the whole source, syntax errors leave error nodes in the tree
+    pub fn parse_with_errors(mut self) -> (Syntax, Vec<Diagnostic>) {
         self.advance();
-        let stmt = self.parse_statement();
-        self.arena.set_root(stmt);
-        Syntax { arena: self.arena }
+        while self.current.get_kind() != token::Kind::EOF {
+            self.parse_item();
+            if self.panicking {
+                self.synchronize_item();
+            }
+        }
+        let syntax = Syntax {
+            arena: self.arena,
+            files: vec![self.file],
+        };
+        (syntax, self.errors)
     }
 
-    fn parse_statement(&mut self) -> statement::Id {
-        match dbg!(self.current.get_kind()) {
-            token::Kind::LBrace => self.parse_block(),
-            token::Kind::Let => unimplemented!(),
+    fn parse_item(&mut self) -> Option<item::Id> {
+        let item = match self.current.get_kind() {
+            token::Kind::Function => self.parse_function(),
+            token::Kind::Const => self.parse_const(),
+            token::Kind::Struct => self.parse_struct(),
+            token::Kind::Enum => self.parse_enum(),
             _ => {
-                let expr = self.parse_expression();
-                self.consume(token::Kind::Semicolon);
-                self.arena.push_epxression_statement(expr)
+                let current = self.current.clone();
+                self.expected("item", &current);
+                self.advance();
+                return None;
+            }
+        };
+        Some(item)
+    }
+
+    /// parse `fn name(a: T, b: U) -> R { .. }`
+    fn parse_function(&mut self) -> item::Id {
+        let start = self.current.byte;
+        self.consume(token::Kind::Function);
+        self.consume(token::Kind::Identifier);
+        let name = self.previous.text.to_owned();
+
+        self.consume(token::Kind::LParen);
+        let (params, _) = self.parse_parenthesized(Self::parse_param);
+        let ret = self.parse_return_type();
+
+        let body = self.parse_block();
+        self.arena
+            .push_function(name, params, ret, body, self.span_from(start))
+    }
+
+    /// parse `name: T`
+    fn parse_param(&mut self) -> item::Param {
+        self.consume(token::Kind::Identifier);
+        let name = self.previous.text.to_owned();
+        self.consume(token::Kind::Colon);
+        let ty = self.parse_type();
+        item::Param { name, ty }
+    }
+
+    /// parse optional `-> T`
+    fn parse_return_type(&mut self) -> Option<ty::Id> {
+        match self.current.get_kind() {
+            token::Kind::Arrow => {
+                self.advance();
+                Some(self.parse_type())
+            }
+            _ => None,
+        }
+    }
+
+    /// parse `const NAME: T = init;`
+    fn parse_const(&mut self) -> item::Id {
+        let start = self.current.byte;
+        self.consume(token::Kind::Const);
+        self.consume(token::Kind::Identifier);
+        let name = self.previous.text.to_owned();
+        self.consume(token::Kind::Colon);
+        let ty = self.parse_type();
+        self.consume(token::Kind::Equal);
+        let init = self.parse_expression();
+        self.consume(token::Kind::Semicolon);
+        self.arena.push_const(name, ty, init, self.span_from(start))
+    }
+
+    /// parse `struct Name { a: T, b: U }`
+    fn parse_struct(&mut self) -> item::Id {
+        let start = self.current.byte;
+        self.consume(token::Kind::Struct);
+        self.consume(token::Kind::Identifier);
+        let name = self.previous.text.to_owned();
+
+        self.consume(token::Kind::LBrace);
+        let (fields, _) = self.parse_delimited(token::Kind::RBrace, |parser| {
+            parser.consume(token::Kind::Identifier);
+            let name = parser.previous.text.to_owned();
+            parser.consume(token::Kind::Colon);
+            let ty = parser.parse_type();
+            item::Field { name, ty }
+        });
+
+        self.arena.push_struct(name, fields, self.span_from(start))
+    }
+
+    /// parse `enum Name { A, B(T, U) }`
+    fn parse_enum(&mut self) -> item::Id {
+        let start = self.current.byte;
+        self.consume(token::Kind::Enum);
+        self.consume(token::Kind::Identifier);
+        let name = self.previous.text.to_owned();
+
+        self.consume(token::Kind::LBrace);
+        let (variants, _) = self.parse_delimited(token::Kind::RBrace, |parser| {
+            parser.consume(token::Kind::Identifier);
+            let name = parser.previous.text.to_owned();
+            let fields = match parser.current.get_kind() {
+                token::Kind::LParen => {
+                    parser.advance();
+                    parser.parse_parenthesized(Self::parse_type).0
+                }
+                _ => Vec::new(),
+            };
+            item::Variant { name, fields }
+        });
+
+        self.arena.push_enum(name, variants, self.span_from(start))
+    }
+
+    /// parse type expression: `name`, `!`, `[T]`, `(T)`, tuple `(T, U)` or `fn(T) -> U`
+    fn parse_type(&mut self) -> ty::Id {
+        if !matches!(
+            self.current.get_kind(),
+            token::Kind::Identifier
+                | token::Kind::Bang
+                | token::Kind::Function
+                | token::Kind::LBracket
+                | token::Kind::LParen
+        ) {
+            let current = self.current.clone();
+            self.expected("type", &current);
+            return self.arena.make_error_type(Self::token_span(&current));
+        }
+
+        self.advance();
+        let start = self.previous.byte;
+        match self.previous.get_kind() {
+            token::Kind::Identifier => {
+                let name = self.previous.text.to_owned();
+                self.arena.make_named_type(name, self.span_from(start))
+            }
+            token::Kind::Bang => self.arena.make_never_type(self.span_from(start)),
+            token::Kind::Function => {
+                self.consume(token::Kind::LParen);
+                let (params, _) = self.parse_parenthesized(Self::parse_type);
+                let ret = self.parse_return_type();
+                self.arena
+                    .make_function_type(params, ret, self.span_from(start))
+            }
+            token::Kind::LBracket => {
+                let element = self.parse_type();
+                self.consume(token::Kind::RBracket);
+                self.arena.make_array_type(element, self.span_from(start))
             }
+            token::Kind::LParen => {
+                let (elements, trailing_comma) = self.parse_parenthesized(Self::parse_type);
+                match (elements.as_slice(), trailing_comma) {
+                    ([inner], false) => *inner,
+                    _ => self.arena.make_tuple_type(elements, self.span_from(start)),
+                }
+            }
+            _ => unreachable!("the first token of a type is checked"),
+        }
+    }
+
+    /// Whether the current token starts an expression ending with a block
+    fn at_block_like(&self) -> bool {
+        matches!(
+            self.current.get_kind(),
+            token::Kind::LBrace
+                | token::Kind::If
+                | token::Kind::While
+                | token::Kind::Label
+                | token::Kind::Match
+        )
+    }
+
+    /// parse `let pattern: ty = init;` with optional type
+    fn parse_let(&mut self) -> statement::Id {
+        let start = self.current.byte;
+        self.consume(token::Kind::Let);
+        let pattern = self.parse_pattern();
+        let ty = match self.current.get_kind() {
+            token::Kind::Colon => {
+                self.advance();
+                Some(self.parse_type())
+            }
+            _ => None,
+        };
+        self.consume(token::Kind::Equal);
+        let init = self.parse_expression();
+        self.consume(token::Kind::Semicolon);
+        self.arena
+            .push_let(pattern, ty, init, self.span_from(start))
+    }
+
+    /// parse `name`, `_`, a literal, tuple pattern `(a, (b, c))` or variant `Enum::A(a, b)`
+    fn parse_pattern(&mut self) -> pattern::Id {
+        if !matches!(
+            self.current.get_kind(),
+            token::Kind::Identifier
+                | token::Kind::Int64Lit(_)
+                | token::Kind::Uint64Lit(_)
+                | token::Kind::FloatLit(_)
+                | token::Kind::Minus
+                | token::Kind::True
+                | token::Kind::False
+                | token::Kind::StringLit
+                | token::Kind::RawStringLit
+                | token::Kind::LParen
+        ) {
+            let current = self.current.clone();
+            self.expected("pattern", &current);
+            return self.arena.make_error_pattern(Self::token_span(&current));
+        }
+
+        self.advance();
+        let start = self.previous.byte;
+        let literal = match self.previous.get_kind() {
+            token::Kind::Identifier if self.previous.text == "_" => {
+                return self.arena.make_wildcard_pattern(self.span_from(start));
+            }
+            token::Kind::Identifier if self.current.get_kind() == token::Kind::ColonColon => {
+                let (enum_name, variant) = self.parse_path();
+                let fields = match self.current.get_kind() {
+                    token::Kind::LParen => {
+                        self.advance();
+                        self.parse_parenthesized(Self::parse_pattern).0
+                    }
+                    _ => Vec::new(),
+                };
+                let span = self.span_from(start);
+                return self
+                    .arena
+                    .make_variant_pattern(enum_name, variant, fields, span);
+            }
+            token::Kind::Identifier => {
+                let name = self.previous.text.to_owned();
+                return self
+                    .arena
+                    .make_identifier_pattern(name, self.span_from(start));
+            }
+            token::Kind::Int64Lit(i) => self.arena.make_int64(i, self.span_from(start)),
+            token::Kind::Uint64Lit(u) => self.arena.make_uint64(u, self.span_from(start)),
+            token::Kind::FloatLit(f) => self.arena.make_float64(f, self.span_from(start)),
+            token::Kind::Minus => match self.current.get_kind() {
+                token::Kind::Int64Lit(i) => {
+                    self.advance();
+                    self.arena.make_int64(-i, self.span_from(start))
+                }
+                token::Kind::FloatLit(f) => {
+                    self.advance();
+                    self.arena.make_float64(-f, self.span_from(start))
+                }
+                _ => {
+                    let current = self.current.clone();
+                    self.expected("number", &current);
+                    return self.arena.make_error_pattern(self.span_from(start));
+                }
+            },
+            token::Kind::True | token::Kind::False => {
+                let value = self.previous.get_kind() == token::Kind::True;
+                self.arena.make_bool(value, self.span_from(start))
+            }
+            token::Kind::StringLit | token::Kind::RawStringLit => {
+                let value = self.previous.string_value();
+                self.arena.make_str(value, self.span_from(start))
+            }
+            token::Kind::LParen => {
+                let (elements, trailing_comma) = self.parse_parenthesized(Self::parse_pattern);
+                return match (elements.as_slice(), trailing_comma) {
+                    ([inner], false) => *inner,
+                    _ => self
+                        .arena
+                        .make_tuple_pattern(elements, self.span_from(start)),
+                };
+            }
+            _ => unreachable!("the first token of a pattern is checked"),
+        };
+        self.arena
+            .make_literal_pattern(literal, self.span_from(start))
+    }
+
+    /// parse `::Variant` after the enum name, returns both names
+    fn parse_path(&mut self) -> (String, String) {
+        let enum_name = self.previous.text.to_owned();
+        self.consume(token::Kind::ColonColon);
+        self.consume(token::Kind::Identifier);
+        (enum_name, self.previous.text.to_owned())
+    }
+
+    /// parse comma separated elements after `(` up to and including `)`
+    ///
+    /// Returns whether the last element was followed by a comma, to tell `(a,)` from `(a)`
+    fn parse_parenthesized<T>(&mut self, element: impl FnMut(&mut Self) -> T) -> (Vec<T>, bool) {
+        self.parse_delimited(token::Kind::RParen, element)
+    }
+
+    /// parse comma separated elements up to and including `close`
+    fn parse_delimited<T>(
+        &mut self,
+        close: token::Kind,
+        mut element: impl FnMut(&mut Self) -> T,
+    ) -> (Vec<T>, bool) {
+        let mut elements = Vec::new();
+        let mut trailing_comma = false;
+        while self.current.get_kind() != close {
+            elements.push(element(self));
+            trailing_comma = self.current.get_kind() == token::Kind::Comma;
+            if trailing_comma {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.consume(close);
+        (elements, trailing_comma)
+    }
+
+    /// parse an expression ending with a block, without any trailing operators
+    fn parse_block_like(&mut self) -> expression::Id {
+        match self.current.get_kind() {
+            token::Kind::LBrace => self.parse_block(),
+            token::Kind::If => self.parse_if(),
+            token::Kind::While | token::Kind::Label => self.parse_while(),
+            token::Kind::Match => self.parse_match(),
+            _ => unreachable!("callers check `at_block_like`"),
         }
     }
 
     /// parse {...} including braces
-    fn parse_block(&mut self) -> statement::Id {
+    ///
+    /// An expression directly before `}` without a semicolon is the block's value
+    fn parse_block(&mut self) -> expression::Id {
+        let start = self.current.byte;
         let mut stmts = Vec::new();
-        self.advance();
+        let mut tail = None;
+        self.consume(token::Kind::LBrace);
 
         loop {
-            if let token::Kind::RBrace = self.current.get_kind() {
+            match self.current.get_kind() {
+                token::Kind::RBrace => {
+                    self.advance();
+                    break;
+                }
+                // items do not nest, the `}` of this block is most likely missing
+                token::Kind::EOF
+                | token::Kind::Function
+                | token::Kind::Const
+                | token::Kind::Struct
+                | token::Kind::Enum => {
+                    self.consume(token::Kind::RBrace);
+                    break;
+                }
+                token::Kind::Semicolon => self.advance(),
+                token::Kind::Let => stmts.push(self.parse_let()),
+                _ => {
+                    let block_like = self.at_block_like();
+                    let expr = match block_like {
+                        true => self.parse_block_like(),
+                        false => self.parse_expression(),
+                    };
+
+                    match self.current.get_kind() {
+                        token::Kind::RBrace => tail = Some(expr),
+                        token::Kind::Semicolon => self.advance(),
+                        _ if block_like => (),
+                        _ => self.consume(token::Kind::Semicolon),
+                    }
+
+                    if tail.is_none() {
+                        let span = self.arena[expr].span;
+                        stmts.push(self.arena.push_epxression_statement(expr, span));
+                    }
+                }
+            }
+            if self.panicking {
+                self.synchronize();
+            }
+        }
+
+        self.arena.make_block(stmts, tail, self.span_from(start))
+    }
+
+    /// parse `if cond { .. } else { .. }` including `else if` chains
+    fn parse_if(&mut self) -> expression::Id {
+        let start = self.current.byte;
+        self.consume(token::Kind::If);
+        let cond = self.parse_condition();
+        let then = self.parse_block();
+
+        let otherwise = match self.current.get_kind() {
+            token::Kind::Else => {
                 self.advance();
-                break;
+                match self.current.get_kind() {
+                    token::Kind::If => Some(self.parse_if()),
+                    _ => Some(self.parse_block()),
+                }
+            }
+            _ => None,
+        };
+
+        self.arena
+            .make_if(cond, then, otherwise, self.span_from(start))
+    }
+
+    /// parse `'label: while cond { .. }` with optional label
+    fn parse_while(&mut self) -> expression::Id {
+        let start = self.current.byte;
+        let label = match self.current.get_kind() {
+            token::Kind::Label => {
+                let label = self.parse_label();
+                self.consume(token::Kind::Colon);
+                Some(label)
+            }
+            _ => None,
+        };
+
+        self.consume(token::Kind::While);
+        let cond = self.parse_condition();
+        let body = self.parse_block();
+        self.arena
+            .make_while(label, cond, body, self.span_from(start))
+    }
+
+    /// parse `match scrutinee { pattern => body, .. }`
+    ///
+    /// The comma after an arm is optional when its body ends with a block
+    fn parse_match(&mut self) -> expression::Id {
+        let start = self.current.byte;
+        self.consume(token::Kind::Match);
+        let scrutinee = self.parse_condition();
+        self.consume(token::Kind::LBrace);
+
+        let mut arms = Vec::new();
+        while !matches!(
+            self.current.get_kind(),
+            token::Kind::RBrace | token::Kind::EOF
+        ) && !self.panicking
+        {
+            let pattern = self.parse_pattern();
+            self.consume(token::Kind::FatArrow);
+
+            let block_like = self.at_block_like();
+            let body = match block_like {
+                true => self.parse_block_like(),
+                false => self.parse_expression(),
+            };
+            arms.push(expression::Arm { pattern, body });
+
+            match self.current.get_kind() {
+                token::Kind::Comma => self.advance(),
+                token::Kind::RBrace => (),
+                _ if block_like => (),
+                _ => self.consume(token::Kind::Comma),
             }
-            let stmt = self.parse_statement();
-            stmts.push(stmt);
         }
+        self.consume(token::Kind::RBrace);
+
+        self.arena
+            .make_match(scrutinee, arms, self.span_from(start))
+    }
 
-        self.arena.push_block(stmts)
+    /// parse `'label` into label name without quote
+    fn parse_label(&mut self) -> String {
+        self.consume(token::Kind::Label);
+        self.previous.text[1..].to_owned()
+    }
+
+    fn parse_optional_label(&mut self) -> Option<String> {
+        match self.current.get_kind() {
+            token::Kind::Label => Some(self.parse_label()),
+            _ => None,
+        }
     }
 
     fn parse_expression(&mut self) -> expression::Id {
-        self.parse_bp(0)
+        self.with_struct_literals(true, |parser| parser.parse_bp(0))
+    }
+
+    /// parse expression followed by a block, where `Name {` is not a struct literal
+    fn parse_condition(&mut self) -> expression::Id {
+        self.with_struct_literals(false, |parser| parser.parse_bp(0))
+    }
+
+    fn with_struct_literals<T>(&mut self, allowed: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = f(self);
+        self.struct_literals = outer;
+        result
     }
 
     fn parse_lhs(&mut self) -> expression::Id {
+        if self.at_block_like() {
+            return self.parse_block_like();
+        }
+
+        // leave closing tokens to whatever they close
+        if matches!(
+            self.current.get_kind(),
+            token::Kind::EOF
+                | token::Kind::Semicolon
+                | token::Kind::Comma
+                | token::Kind::RParen
+                | token::Kind::RBracket
+                | token::Kind::RBrace
+        ) {
+            let current = self.current.clone();
+            self.expected("expression", &current);
+            return self.arena.make_error(Self::token_span(&current));
+        }
+
         self.advance();
+        let start = self.previous.byte;
         match self.previous.get_kind() {
-            token::Kind::Int64Lit(i) => self.arena.make_int64(i),
-            token::Kind::Uint64Lit(u) => self.arena.make_uint64(u),
-            token::Kind::FloatLit(f) => self.arena.make_float64(f),
-            token::Kind::True => self.arena.make_bool(true),
-            token::Kind::False => self.arena.make_bool(false),
+            token::Kind::Int64Lit(i) => self.arena.make_int64(i, self.span_from(start)),
+            token::Kind::Uint64Lit(u) => self.arena.make_uint64(u, self.span_from(start)),
+            token::Kind::FloatLit(f) => self.arena.make_float64(f, self.span_from(start)),
+            token::Kind::True => self.arena.make_bool(true, self.span_from(start)),
+            token::Kind::False => self.arena.make_bool(false, self.span_from(start)),
+            token::Kind::StringLit | token::Kind::RawStringLit => {
+                let value = self.previous.string_value();
+                self.arena.make_str(value, self.span_from(start))
+            }
+            token::Kind::Identifier
+                if self.struct_literals && self.current.get_kind() == token::Kind::LBrace =>
+            {
+                self.parse_struct_literal()
+            }
+            token::Kind::Identifier if self.current.get_kind() == token::Kind::ColonColon => {
+                self.parse_variant()
+            }
+            token::Kind::Identifier => {
+                let name = self.previous.text.to_owned();
+                self.arena.make_identifier(name, self.span_from(start))
+            }
+            token::Kind::Break => {
+                let label = self.parse_optional_label();
+                self.arena.make_break(label, self.span_from(start))
+            }
+            token::Kind::Continue => {
+                let label = self.parse_optional_label();
+                self.arena.make_continue(label, self.span_from(start))
+            }
+            token::Kind::Return => {
+                let value = match self.current.get_kind() {
+                    token::Kind::Semicolon | token::Kind::RBrace => None,
+                    _ => Some(self.parse_expression()),
+                };
+                self.arena.make_return(value, self.span_from(start))
+            }
+            token::Kind::Pipe => {
+                let (params, _) = self.parse_delimited(token::Kind::Pipe, Self::parse_param);
+                self.parse_lambda(start, params)
+            }
+            token::Kind::OrOr => self.parse_lambda(start, Vec::new()),
+            token::Kind::LBracket => {
+                let (elements, _) =
+                    self.parse_delimited(token::Kind::RBracket, Self::parse_expression);
+                self.arena.make_array(elements, self.span_from(start))
+            }
             token::Kind::LParen => {
-                let exp = self.parse_bp(0);
-                self.consume(token::Kind::RParen);
-                exp
+                let (elements, trailing_comma) = self.parse_parenthesized(Self::parse_expression);
+                match (elements.as_slice(), trailing_comma) {
+                    ([], _) => self.arena.make_unit(self.span_from(start)),
+                    ([inner], false) => *inner,
+                    _ => self.arena.make_tuple(elements, self.span_from(start)),
+                }
             }
             t => {
                 if let Some(op) = operator::Prefix::get(t) {
                     let rbp = op.get_bp();
                     let exp = self.parse_bp(rbp);
-                    self.arena.make_prefix(op, exp)
+                    self.arena.make_prefix(op, exp, self.span_from(start))
                 } else {
-                    panic!("Unexpected TokenKind: {t:?}");
+                    let previous = self.previous.clone();
+                    self.expected("expression", &previous);
+                    self.arena.make_error(self.span_from(start))
                 }
             }
         }
@@ -113,8 +747,17 @@ impl<'s> Parser<'s> {
                     break;
                 }
 
-                self.advance();
-                lhs = self.arena.make_postfix(op, lhs);
+                lhs = match op {
+                    operator::Postfix::Call => self.parse_call(lhs),
+                    operator::Postfix::Field => self.parse_field(lhs),
+                    operator::Postfix::Index => self.parse_index(lhs),
+                    operator::Postfix::Cast => {
+                        self.advance();
+                        let ty = self.parse_type();
+                        let span = self.span_from(self.arena[lhs].span.start);
+                        self.arena.make_cast(lhs, ty, span)
+                    }
+                };
                 continue;
             }
 
@@ -126,18 +769,209 @@ impl<'s> Parser<'s> {
 
                 self.advance();
                 let rhs = self.parse_bp(rbp);
-                lhs = self.arena.make_infix(op, lhs, rhs);
+                let span = self.arena[lhs].span.to(self.arena[rhs].span);
+                lhs = self.arena.make_infix(op, lhs, rhs, span);
                 continue;
             }
 
-            match op {
-                token::Kind::EOF | token::Kind::RParen | token::Kind::Semicolon => break,
-                _ => {
-                    panic!("Unexpected operation: {op:?}");
+            // nothing else continues an expression, what the caller expects
+            // next is reported by the caller
+            break;
+        }
+
+        lhs
+    }
+
+    /// parse `Name { a: x, b: y }` after the name
+    fn parse_struct_literal(&mut self) -> expression::Id {
+        let start = self.previous.byte;
+        let name = self.previous.text.to_owned();
+        self.consume(token::Kind::LBrace);
+        let (fields, _) = self.parse_delimited(token::Kind::RBrace, |parser| {
+            parser.consume(token::Kind::Identifier);
+            let name = parser.previous.text.to_owned();
+            parser.consume(token::Kind::Colon);
+            (name, parser.parse_expression())
+        });
+        self.arena
+            .make_struct_literal(name, fields, self.span_from(start))
+    }
+
+    /// parse the rest of `|params| -> R { .. }` or `|params| body` after the parameters
+    fn parse_lambda(&mut self, start: usize, params: Vec<item::Param>) -> expression::Id {
+        let ret = self.parse_return_type();
+        let body = match ret {
+            Some(_) => self.parse_block(),
+            None => self.parse_expression(),
+        };
+        self.arena
+            .make_lambda(params, ret, body, self.span_from(start))
+    }
+
+    /// parse `Enum::Variant(args)` after the enum name
+    fn parse_variant(&mut self) -> expression::Id {
+        let start = self.previous.byte;
+        let (enum_name, variant) = self.parse_path();
+        let args = match self.current.get_kind() {
+            token::Kind::LParen => {
+                self.advance();
+                self.parse_parenthesized(Self::parse_expression).0
+            }
+            _ => Vec::new(),
+        };
+        self.arena
+            .make_variant(enum_name, variant, args, self.span_from(start))
+    }
+
+    /// parse `.field` or `.0` after object, `.0.1` is lexed as a single float literal
+    fn parse_field(&mut self, object: expression::Id) -> expression::Id {
+        self.consume(token::Kind::Dot);
+        self.advance();
+        let span = self.span_from(self.arena[object].span.start);
+        match self.previous.get_kind() {
+            token::Kind::Identifier => {
+                let name = self.previous.text.to_owned();
+                self.arena.make_field(object, name, span)
+            }
+            token::Kind::Int64Lit(_) | token::Kind::FloatLit(_) => {
+                // only plain decimal digits, `t.0x1` or `t.1e2` are not indices
+                let indices = self
+                    .previous
+                    .text
+                    .split('.')
+                    .map(|index| match index.bytes().all(|b| b.is_ascii_digit()) {
+                        true => index.parse::<usize>().ok(),
+                        false => None,
+                    })
+                    .collect::<Option<Vec<_>>>();
+                match indices {
+                    Some(indices) => indices.into_iter().fold(object, |object, index| {
+                        self.arena.make_tuple_index(object, index, span)
+                    }),
+                    None => {
+                        let previous = self.previous.clone();
+                        self.expected("tuple index", &previous);
+                        self.arena.make_error(span)
+                    }
                 }
             }
+            _ => {
+                let previous = self.previous.clone();
+                self.expected("field name", &previous);
+                self.arena.make_error(span)
+            }
         }
+    }
 
-        lhs
+    /// parse `[index]` after array
+    fn parse_index(&mut self, array: expression::Id) -> expression::Id {
+        self.consume(token::Kind::LBracket);
+        let index = self.parse_expression();
+        self.consume(token::Kind::RBracket);
+        let span = self.span_from(self.arena[array].span.start);
+        self.arena.make_index(array, index, span)
+    }
+
+    /// parse `(args, ...)` after callee
+    fn parse_call(&mut self, callee: expression::Id) -> expression::Id {
+        self.consume(token::Kind::LParen);
+        let (args, _) = self.parse_parenthesized(Self::parse_expression);
+        let span = self.span_from(self.arena[callee].span.start);
+        self.arena.make_call(callee, args, span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(source: &str) -> Vec<String> {
+        match Parser::new(Lexer::new(source)).parse() {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|error| error.message).collect(),
+        }
+    }
+
+    #[test]
+    fn every_broken_statement_is_reported() {
+        let source = "
+            fn main() -> i64 {
+                let a = ;
+                let b: = 2;
+                a b
+            }
+            fn other() -> i64 { 1 }
+        ";
+        assert_eq!(
+            errors(source),
+            [
+                "expected expression, found `;`",
+                "expected type, found `=`",
+                "expected `;`, found `b`",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_semicolons_are_reported_after_the_statement() {
+        let source = "fn main() {\n    let x = 1\n    let y = 2;\n}";
+        let Err(errors) = Parser::new(Lexer::new(source)).parse() else {
+            panic!("`;` is missing");
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "expected `;`, found `let`");
+        let files = [SourceFile::new("main.rl", source)];
+        let span = errors[0].primary_span().expect("errors are located");
+        assert_eq!(
+            (files[0].line(span.start), files[0].column(span.start)),
+            (2, 14)
+        );
+    }
+
+    #[test]
+    fn broken_items_are_skipped() {
+        let source = "fn one( { } } fn two() -> i64 { 1 @ 2 } struct";
+        assert_eq!(
+            errors(source),
+            [
+                "expected identifier, found `{`",
+                "Unexpected character",
+                "expected identifier, found end of file",
+            ]
+        );
+    }
+
+    #[test]
+    fn tuple_indices_are_decimal() {
+        assert!(errors("fn main() { let t = ((1, 2), 3); t.0.1; t.1; }").is_empty());
+        assert_eq!(
+            errors("fn main() { let t = (1, 2); t.0x1; }"),
+            ["expected tuple index, found `0x1`"]
+        );
+    }
+
+    #[test]
+    fn bad_escapes_are_one_error() {
+        let source = r#"fn main() { let s = "bad \q"; println(s); }"#;
+        assert_eq!(errors(source), ["Unknown escape sequence"]);
+    }
+
+    #[test]
+    fn errors_leave_placeholders_in_the_tree() {
+        let parser = Parser::new(Lexer::new("fn main() -> i64 { let x = ; x }"));
+        let (syntax, errors) = parser.parse_with_errors();
+        assert_eq!(errors.len(), 1);
+        let arena = &syntax.arena;
+        let item::Kind::Function { body, .. } = &arena[arena.item_ids().next().unwrap()].kind
+        else {
+            panic!("main is a function");
+        };
+        let expression::Kind::Block { stmts, .. } = &arena[*body].kind else {
+            panic!("function bodies are blocks");
+        };
+        let statement::Kind::Let { init, .. } = &arena[stmts[0]].kind else {
+            panic!("first statement is the let");
+        };
+        assert!(matches!(arena[*init].kind, expression::Kind::Error));
     }
 }
@@ -1,4 +1,4 @@
-use ptree::{TreeBuilder, item::StringItem, print_tree};
+use ptree::{TreeBuilder, print_tree};
 
 use crate::grammar::*;
 
@@ -16,21 +16,60 @@ impl<'s> TreePrinter<'s> {
     }
 
     pub fn print(mut self) {
-        let root = self.syntax.arena.get_root();
-        self.add_statement(root);
+        for id in self.syntax.arena.item_ids() {
+            self.add_item(id);
+        }
         let tree = self.builder.build();
         print_tree(&tree).unwrap();
     }
 
+    fn with_label(name: &str, label: &Option<String>) -> String {
+        match label {
+            Some(label) => format!("{name}('{label})"),
+            None => name.to_owned(),
+        }
+    }
+
     fn get_label(kind: &expression::Kind) -> String {
         match kind {
             expression::Kind::Int64(i) => format!("Int64({i})"),
             expression::Kind::Uint64(u) => format!("Uint64({u})"),
             expression::Kind::Float64(f) => format!("Float64({f})"),
             expression::Kind::Bool(b) => format!("Bool({b})"),
+            expression::Kind::Str(s) => format!("Str({s:?})"),
             expression::Kind::Unit => "Unit".to_owned(),
+            expression::Kind::Error => "Error".to_owned(),
+            expression::Kind::Identifier(name) => format!("Identifier({name})"),
+            expression::Kind::Tuple(_) => "Tuple".to_owned(),
+            expression::Kind::Array(_) => "Array".to_owned(),
+            expression::Kind::Variant {
+                enum_name,
+                variant,
+                args: _,
+            } => format!("Variant({enum_name}::{variant})"),
+            expression::Kind::StructLiteral { name, fields: _ } => format!("StructLiteral({name})"),
+            expression::Kind::Field { object: _, name } => format!("Field({name})"),
+            expression::Kind::Index { array: _, index: _ } => "Index".to_owned(),
+            expression::Kind::TupleIndex { tuple: _, index } => format!("TupleIndex({index})"),
             expression::Kind::Infix { op, lhs: _, rhs: _ } => op.to_string(),
             expression::Kind::Prefix { op, exp: _ } => op.to_string(),
+            expression::Kind::Cast { .. } => "Cast".to_owned(),
+            expression::Kind::Block { stmts: _, tail: _ } => "Block".to_owned(),
+            expression::Kind::If {
+                cond: _,
+                then: _,
+                otherwise: _,
+            } => "If".to_owned(),
+            expression::Kind::While { label, .. } => Self::with_label("While", label),
+            expression::Kind::Match {
+                scrutinee: _,
+                arms: _,
+            } => "Match".to_owned(),
+            expression::Kind::Break { label } => Self::with_label("Break", label),
+            expression::Kind::Continue { label } => Self::with_label("Continue", label),
+            expression::Kind::Call { callee: _, args: _ } => "Call".to_owned(),
+            expression::Kind::Lambda { .. } => "Lambda".to_owned(),
+            expression::Kind::Return(_) => "Return".to_owned(),
         }
     }
 
@@ -38,45 +77,295 @@ impl<'s> TreePrinter<'s> {
         use expression::Kind::*;
 
         let kind = &self.syntax.arena[id].kind;
-        let label = TreePrinter::get_label(kind);
+        let label = match kind {
+            Lambda {
+                params,
+                ret: Some(ret),
+                ..
+            } => format!(
+                "Lambda({}) -> {}",
+                self.param_list(params),
+                self.type_name(*ret)
+            ),
+            Lambda { params, .. } => format!("Lambda({})", self.param_list(params)),
+            Cast { ty, .. } => format!("Cast({})", self.type_name(*ty)),
+            _ => TreePrinter::get_label(kind),
+        };
         self.builder.begin_child(label);
 
         match kind {
-            Int64(_) | Uint64(_) | Float64(_) | Bool(_) | Unit => (),
+            Int64(_) | Uint64(_) | Float64(_) | Bool(_) | Str(_) | Unit | Identifier(_) => (),
+            Error => (),
+            Break { label: _ } | Continue { label: _ } => (),
+
+            Call { callee, args } => {
+                self.add_expression(*callee);
+                for arg in args {
+                    self.add_expression(*arg);
+                }
+            }
+
+            Tuple(elements) | Array(elements) | Variant { args: elements, .. } => {
+                for element in elements {
+                    self.add_expression(*element);
+                }
+            }
+
+            Index { array, index } => {
+                self.add_expression(*array);
+                self.add_expression(*index);
+            }
+
+            StructLiteral { name: _, fields } => {
+                for (name, value) in fields {
+                    self.builder.begin_child(format!("{name}:"));
+                    self.add_expression(*value);
+                    self.builder.end_child();
+                }
+            }
+
+            Field { object, name: _ } => {
+                self.add_expression(*object);
+            }
+
+            TupleIndex { tuple, index: _ } => {
+                self.add_expression(*tuple);
+            }
+
+            Return(value) => {
+                if let Some(value) = value {
+                    self.add_expression(*value);
+                }
+            }
 
             Infix { lhs, rhs, op: _ } => {
                 self.add_expression(*lhs);
                 self.add_expression(*rhs);
             }
 
-            Prefix { exp, op: _ } => {
+            Prefix { exp, op: _ } | Cast { exp, ty: _ } => {
                 self.add_expression(*exp);
             }
+
+            Lambda {
+                params: _,
+                ret: _,
+                body,
+            } => {
+                self.add_expression(*body);
+            }
+
+            Block { stmts, tail } => {
+                for stmt in stmts {
+                    self.add_statement(*stmt);
+                }
+                if let Some(tail) = tail {
+                    self.add_expression(*tail);
+                }
+            }
+
+            If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                self.add_expression(*cond);
+                self.add_expression(*then);
+                if let Some(otherwise) = otherwise {
+                    self.add_expression(*otherwise);
+                }
+            }
+
+            While {
+                label: _,
+                cond,
+                body,
+            } => {
+                self.add_expression(*cond);
+                self.add_expression(*body);
+            }
+
+            Match { scrutinee, arms } => {
+                self.add_expression(*scrutinee);
+                for arm in arms {
+                    let pattern = self.pattern_name(arm.pattern);
+                    self.builder.begin_child(format!("{pattern} =>"));
+                    self.add_expression(arm.body);
+                    self.builder.end_child();
+                }
+            }
         };
 
         self.builder.end_child();
     }
 
+    fn add_item(&mut self, id: item::Id) {
+        let kind = &self.syntax.arena[id].kind;
+        match kind {
+            item::Kind::Function {
+                name,
+                params,
+                ret,
+                body,
+            } => {
+                let params = self.param_list(params);
+                let ret = match ret {
+                    Some(ret) => self.type_name(*ret),
+                    None => "()".to_owned(),
+                };
+                self.builder
+                    .begin_child(format!("Function {name}({params}) -> {ret}"));
+                self.add_expression(*body);
+                self.builder.end_child();
+            }
+            item::Kind::Const { name, ty, init } => {
+                let ty = self.type_name(*ty);
+                self.builder.begin_child(format!("Const {name}: {ty}"));
+                self.add_expression(*init);
+                self.builder.end_child();
+            }
+            item::Kind::Struct { name, fields } => {
+                self.builder.begin_child(format!("Struct {name}"));
+                for field in fields {
+                    let ty = self.type_name(field.ty);
+                    self.builder
+                        .add_empty_child(format!("{}: {ty}", field.name));
+                }
+                self.builder.end_child();
+            }
+            item::Kind::Enum { name, variants } => {
+                self.builder.begin_child(format!("Enum {name}"));
+                for variant in variants {
+                    let label = match variant.fields.is_empty() {
+                        true => variant.name.clone(),
+                        false => {
+                            let fields = variant
+                                .fields
+                                .iter()
+                                .map(|field| self.type_name(*field))
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            format!("{}({fields})", variant.name)
+                        }
+                    };
+                    self.builder.add_empty_child(label);
+                }
+                self.builder.end_child();
+            }
+        }
+    }
+
+    /// `a: T, b: U` of a function or lambda
+    fn param_list(&self, params: &[item::Param]) -> String {
+        params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, self.type_name(param.ty)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn type_name(&self, id: ty::Id) -> String {
+        match &self.syntax.arena[id].kind {
+            ty::Kind::Named(name) => name.to_owned(),
+            ty::Kind::Never => "!".to_owned(),
+            ty::Kind::Error => "{error}".to_owned(),
+            ty::Kind::Array(element) => format!("[{}]", self.type_name(*element)),
+            ty::Kind::Function { params, ret } => {
+                let params = params
+                    .iter()
+                    .map(|param| self.type_name(*param))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match ret {
+                    Some(ret) => format!("fn({params}) -> {}", self.type_name(*ret)),
+                    None => format!("fn({params})"),
+                }
+            }
+            ty::Kind::Tuple(elements) if elements.len() == 1 => {
+                format!("({},)", self.type_name(elements[0]))
+            }
+            ty::Kind::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|el| self.type_name(*el))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("({elements})")
+            }
+        }
+    }
+
+    fn pattern_name(&self, id: pattern::Id) -> String {
+        match &self.syntax.arena[id].kind {
+            pattern::Kind::Identifier(name) => name.to_owned(),
+            pattern::Kind::Wildcard => "_".to_owned(),
+            pattern::Kind::Error => "{error}".to_owned(),
+            pattern::Kind::Literal(literal) => {
+                let kind = &self.syntax.arena[*literal].kind;
+                match kind {
+                    expression::Kind::Str(s) => format!("{s:?}"),
+                    expression::Kind::Int64(i) => i.to_string(),
+                    expression::Kind::Uint64(u) => format!("{u}u64"),
+                    expression::Kind::Float64(f) => format!("{f:?}"),
+                    expression::Kind::Bool(b) => b.to_string(),
+                    kind => Self::get_label(kind),
+                }
+            }
+            pattern::Kind::Variant {
+                enum_name,
+                variant,
+                fields,
+            } if fields.is_empty() => format!("{enum_name}::{variant}"),
+            pattern::Kind::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                let fields = fields
+                    .iter()
+                    .map(|field| self.pattern_name(*field))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{enum_name}::{variant}({fields})")
+            }
+            pattern::Kind::Tuple(elements) if elements.len() == 1 => {
+                format!("({},)", self.pattern_name(elements[0]))
+            }
+            pattern::Kind::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|el| self.pattern_name(*el))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("({elements})")
+            }
+        }
+    }
+
     fn add_statement(&mut self, id: statement::Id) {
         use statement::Kind::*;
 
         let kind = &self.syntax.arena[id].kind;
         let label = match kind {
-            Block(_) => "Block Statement",
-            Expression(_) => "Expression Statement",
-            Let { name: _, init: _ } => unimplemented!(),
-        }
-        .to_owned();
+            Expression(_) => "Expression Statement".to_owned(),
+            Let {
+                pattern, ty: None, ..
+            } => format!("Let Statement({})", self.pattern_name(*pattern)),
+            Let {
+                pattern,
+                ty: Some(ty),
+                ..
+            } => format!(
+                "Let Statement({}: {})",
+                self.pattern_name(*pattern),
+                self.type_name(*ty)
+            ),
+        };
         self.builder.begin_child(label);
 
         match kind {
             Expression(exp) => self.add_expression(*exp),
-            Block(stmts) => {
-                for stmt in stmts {
-                    self.add_statement(*stmt);
-                }
-            }
-            _ => unimplemented!(),
+            Let { init, .. } => self.add_expression(*init),
         };
 
         self.builder.end_child();
@@ -5,4 +5,6 @@ pub struct Function {
     pub name: String,
     pub chunk: Chunk,
     pub arity: u8,
+    /// Stack slots reserved for locals after the arguments
+    pub locals: u16,
 }
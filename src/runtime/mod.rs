@@ -1,8 +1,11 @@
 mod function;
+mod native;
 mod object;
 mod program;
 mod value;
 
 pub use function::Function;
+pub use native::{Native, NativeFunction};
+pub use object::Object;
 pub use program::Program;
 pub use value::Value;
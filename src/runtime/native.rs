@@ -0,0 +1,26 @@
+use crate::runtime::Value;
+use crate::semantic::Type;
+use crate::vm::{Result, Vm};
+
+/// Host function called with its arguments in order, the arity and types are checked statically
+pub type NativeFunction = fn(&mut Vm, &[Value]) -> Result<Value>;
+
+/// Function implemented in Rust and callable from Rail like any other function
+#[derive(Debug, Clone)]
+pub struct Native {
+    pub name: String,
+    pub params: Vec<Type>,
+    pub ret: Type,
+    pub function: NativeFunction,
+}
+
+impl Native {
+    pub fn new(name: &str, params: Vec<Type>, ret: Type, function: NativeFunction) -> Self {
+        Self {
+            name: name.to_owned(),
+            params,
+            ret,
+            function,
+        }
+    }
+}
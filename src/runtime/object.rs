@@ -1,7 +1,24 @@
+use crate::runtime::Value;
+
 #[derive(Debug)]
 pub enum Object {
-    Function(ObjRef),
-    // NativeFunction(NativeFunction),
+    /// Function value, the upvalues are captured locals passed after the arguments
+    Closure {
+        function: u16,
+        upvalues: Vec<Value>,
+    },
+    Tuple(Vec<Value>),
+    String(String),
+    Array(Vec<Value>),
+    /// Fields in declaration order
+    Struct(Vec<Value>),
+    /// Enum value, the tag is the variant's position in the declaration
+    Variant {
+        tag: u16,
+        fields: Vec<Value>,
+    },
+    /// Host function value, by its index in `Program.natives`
+    NativeFunction(u16),
 }
 
 pub type ObjRef = usize;
@@ -1,21 +1,37 @@
-use crate::runtime::function::Function;
+use crate::grammar::SourceFile;
+use crate::runtime::{Function, Native};
 
 #[derive(Debug)]
 pub struct Program {
     pub functions: Vec<Function>,
-    // pub native: Vec<_>,
+    /// Host functions, called by index with `CallNative`
+    pub natives: Vec<Native>,
     pub entry: usize,
+    /// Sources the spans of the chunks point into
+    pub files: Vec<SourceFile>,
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Program {
     pub fn new() -> Self {
         Self {
             functions: Vec::new(),
+            natives: Vec::new(),
             entry: 0,
+            files: Vec::new(),
         }
     }
 
     pub fn get_function(&self, idx: u16) -> &Function {
         &self.functions[idx as usize]
     }
+
+    pub fn get_native(&self, idx: u16) -> &Native {
+        &self.natives[idx as usize]
+    }
 }
@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+/// Host function that cannot be made visible to programs
+#[derive(Debug, Error, PartialEq)]
+pub enum RegisterError {
+    #[error("host function `{0}` is already registered")]
+    Duplicate(String),
+    #[error("`{0}` is a built-in function and cannot be replaced")]
+    Builtin(String),
+}
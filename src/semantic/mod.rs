@@ -1,5 +1,8 @@
+mod error;
 mod r#type;
 mod typeenv;
 
+pub use error::RegisterError;
 pub use r#type::Type;
+pub(crate) use typeenv::BUILTINS;
 pub use typeenv::TypeEnv;
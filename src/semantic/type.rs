@@ -1,8 +1,64 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Type {
     Int64,
     Uint64,
     Float64,
     Bool,
+    /// Heap allocated string
+    Str,
     Unit,
+    /// Type of diverging expressions, coerces to any other type
+    Never,
+    /// Non-empty tuple, the empty one is `Unit`
+    Tuple(Vec<Type>),
+    /// Growable array on the heap, `[!]` is the type of `[]`
+    Array(Box<Type>),
+    /// User-defined struct, by name
+    Struct(String),
+    /// User-defined enum, by name
+    Enum(String),
+    /// Function or closure value taking parameters and returning a value
+    Fn(Vec<Type>, Box<Type>),
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Type::*;
+
+        let label = match self {
+            Int64 => "i64",
+            Uint64 => "u64",
+            Float64 => "f64",
+            Bool => "bool",
+            Str => "str",
+            Unit => "()",
+            Never => "!",
+            Array(element) => return write!(f, "[{element}]"),
+            Fn(params, ret) => {
+                let params = params
+                    .iter()
+                    .map(|param| param.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return match **ret {
+                    Unit => write!(f, "fn({params})"),
+                    _ => write!(f, "fn({params}) -> {ret}"),
+                };
+            }
+            Struct(name) | Enum(name) => name,
+            Tuple(elements) if elements.len() == 1 => return write!(f, "({},)", elements[0]),
+            Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|el| el.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return write!(f, "({elements})");
+            }
+        };
+
+        f.write_str(label)
+    }
 }
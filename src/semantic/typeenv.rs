@@ -1,12 +1,26 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use super::Type;
+use super::{RegisterError, Type};
 use crate::grammar::*;
+use crate::runtime::{Native, NativeFunction};
+
+/// Built-in functions, compiled to their own opcodes
+pub(crate) const BUILTINS: [&str; 5] = ["panic", "println", "len", "push", "pop"];
 
 #[derive(Debug)]
 pub struct TypeEnv {
     pub(crate) infix: HashMap<(operator::Infix, Type, Type), Type>,
     pub(crate) prefix: HashMap<(operator::Prefix, Type), Type>,
+    /// Allowed `from as to` conversions
+    pub(crate) casts: HashSet<(Type, Type)>,
+    /// Host functions visible to programs, in registration order
+    pub(crate) natives: Vec<Native>,
+}
+
+impl Default for TypeEnv {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TypeEnv {
@@ -14,7 +28,27 @@ impl TypeEnv {
         Self {
             infix: Self::default_infix(),
             prefix: Self::default_prefix(),
+            casts: Self::default_casts(),
+            natives: Vec::new(),
+        }
+    }
+
+    /// Make a host function callable by name, each name can be registered once
+    pub fn register_native(
+        &mut self,
+        name: &str,
+        params: Vec<Type>,
+        ret: Type,
+        function: NativeFunction,
+    ) -> Result<(), RegisterError> {
+        if BUILTINS.contains(&name) {
+            return Err(RegisterError::Builtin(name.to_owned()));
+        }
+        if self.natives.iter().any(|native| native.name == name) {
+            return Err(RegisterError::Duplicate(name.to_owned()));
         }
+        self.natives.push(Native::new(name, params, ret, function));
+        Ok(())
     }
 
     fn default_infix() -> HashMap<(operator::Infix, Type, Type), Type> {
@@ -24,20 +58,44 @@ impl TypeEnv {
         let mut infix = HashMap::new();
 
         let numeric = [Int64, Uint64, Float64];
-        for &ty in &numeric {
-            infix.insert((Plus, ty, ty), ty);
-            infix.insert((Minus, ty, ty), ty);
-            infix.insert((Mul, ty, ty), ty);
-            infix.insert((Div, ty, ty), ty);
+        for ty in &numeric {
+            for op in [Plus, Minus, Mul, Div, Rem] {
+                infix.insert((op, ty.clone(), ty.clone()), ty.clone());
+            }
+        }
+
+        for ty in &numeric {
+            for op in [Equal, NotEqual, Less, LessEqual, Greater, GreaterEqual] {
+                infix.insert((op, ty.clone(), ty.clone()), Bool);
+            }
         }
 
-        for ty in [Int64, Uint64, Float64] {
-            infix.insert((Equal, ty, ty), Bool);
-            infix.insert((NotEqual, ty, ty), Bool);
-            infix.insert((Less, ty, ty), Bool);
-            infix.insert((LessEqual, ty, ty), Bool);
-            infix.insert((Greater, ty, ty), Bool);
-            infix.insert((GreaterEqual, ty, ty), Bool);
+        infix.insert((Plus, Str, Str), Str);
+        for op in [Equal, NotEqual, Less, LessEqual, Greater, GreaterEqual] {
+            infix.insert((op, Str, Str), Bool);
+        }
+
+        infix.insert((And, Bool, Bool), Bool);
+        infix.insert((Or, Bool, Bool), Bool);
+
+        for ty in [Int64, Uint64] {
+            for op in [
+                WrappingPlus,
+                WrappingMinus,
+                WrappingMul,
+                SaturatingPlus,
+                SaturatingMinus,
+                SaturatingMul,
+            ] {
+                infix.insert((op, ty.clone(), ty.clone()), ty.clone());
+            }
+        }
+
+        // the shift amount has the type of the shifted value
+        for ty in [Int64, Uint64] {
+            for op in [BitAnd, BitOr, BitXor, Shl, Shr] {
+                infix.insert((op, ty.clone(), ty.clone()), ty.clone());
+            }
         }
 
         infix
@@ -54,6 +112,24 @@ impl TypeEnv {
 
         prefix.insert((Negate, Bool), Bool);
 
+        prefix.insert((BitNot, Int64), Int64);
+        prefix.insert((BitNot, Uint64), Uint64);
+
         prefix
     }
+
+    /// Every numeric type converts to every other one, and to itself
+    fn default_casts() -> HashSet<(Type, Type)> {
+        use Type::*;
+
+        let numeric = [Int64, Uint64, Float64];
+        let mut casts = HashSet::new();
+        for from in &numeric {
+            for to in &numeric {
+                casts.insert((from.clone(), to.clone()));
+            }
+        }
+
+        casts
+    }
 }
@@ -1,53 +1,606 @@
 use std::collections::HashMap;
 
+use super::constant::*;
+use super::exhaustive::*;
 use super::*;
+use crate::diagnostic::Diagnostic;
 use crate::grammar::*;
 use crate::module::Module;
+use crate::runtime::Value;
 use crate::semantic::*;
 
+/// Parameter and return types of a function
+#[derive(Debug, Clone)]
+struct Signature {
+    params: Vec<Type>,
+    ret: Type,
+}
+
+/// Lambda being checked, with the enclosing locals it refers to
+#[derive(Debug)]
+struct Closure {
+    /// Index of the first scope inside the lambda, locals below it are captured
+    depth: usize,
+    captures: Vec<String>,
+}
+
 pub struct Typer<'e> {
     env: &'e TypeEnv,
+    functions: HashMap<String, Signature>,
+    /// Field names and types of structs in declaration order
+    structs: HashMap<String, Vec<(String, Type)>>,
+    /// Variants of enums in declaration order
+    enums: HashMap<String, Variants>,
+    /// Declared types and initializers of global constants
+    consts: HashMap<String, (Type, expression::Id)>,
+    /// Folded constants, `None` while the constant is being folded
+    values: HashMap<String, Option<Value>>,
+    /// Return type of the function being checked
+    ret: Type,
+    /// Whether `ret` is inferred from the returns of a lambda without `-> R`
+    infer_ret: bool,
+    /// Enclosing lambdas of the expression being checked, innermost last
+    closures: Vec<Closure>,
+    /// Captured locals of every checked lambda
+    captures: HashMap<expression::Id, Vec<String>>,
+    /// Lexical scopes of local bindings, innermost last
+    scopes: Vec<HashMap<String, Type>>,
+    /// Labels of enclosing loops, innermost last
+    loops: Vec<Option<String>>,
+    /// Innermost node the first error was reported from
+    error_span: Option<Span>,
+    warnings: Vec<Diagnostic>,
 }
 
 impl<'e> Typer<'e> {
     pub fn new(env: &'e TypeEnv) -> Self {
-        Self { env }
+        Self {
+            env,
+            functions: HashMap::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            consts: HashMap::new(),
+            values: HashMap::new(),
+            ret: Type::Unit,
+            infer_ret: false,
+            closures: Vec::new(),
+            captures: HashMap::new(),
+            scopes: Vec::new(),
+            loops: Vec::new(),
+            error_span: None,
+            warnings: Vec::new(),
+        }
     }
 
-    pub fn check(&self, syntax: Syntax) -> Result<Module> {
-        let mut types = HashMap::new();
-        let root = syntax.arena.get_root();
-        self.check_statement(&syntax.arena, &mut types, root)?;
-        let module = Module { syntax, types };
+    pub fn check(mut self, syntax: Syntax) -> std::result::Result<Module, TypeError> {
+        let types = match self.check_items(&syntax.arena) {
+            Ok(types) => types,
+            Err(error) => {
+                let span = self.error_span;
+                let line = span.map_or(0, |span| syntax.line(span));
+                return Err(TypeError { error, span, line });
+            }
+        };
+
+        let consts = self
+            .values
+            .into_iter()
+            .map(|(name, value)| (name, value.expect("all constants are folded")))
+            .collect();
+        let module = Module {
+            syntax,
+            types,
+            consts,
+            captures: self.captures,
+            natives: self.env.natives.clone(),
+            warnings: self.warnings,
+        };
         Ok(module)
     }
 
+    fn check_items(&mut self, arena: &Arena) -> Result<HashMap<expression::Id, Type>> {
+        let mut types = HashMap::new();
+
+        // type names first, so any signature or field can refer to them
+        for id in arena.item_ids() {
+            match &arena[id].kind {
+                item::Kind::Struct { name, .. } => {
+                    let result = self.check_unique_type(name);
+                    self.locate(result, arena[id].span)?;
+                    self.structs.insert(name.clone(), Vec::new());
+                }
+                item::Kind::Enum { name, .. } => {
+                    let result = self.check_unique_type(name);
+                    self.locate(result, arena[id].span)?;
+                    self.enums.insert(name.clone(), Vec::new());
+                }
+                _ => (),
+            }
+        }
+
+        // host functions are called like the ones defined in the program,
+        // their names are checked when registering them
+        for native in &self.env.natives {
+            let signature = Signature {
+                params: native.params.clone(),
+                ret: native.ret.clone(),
+            };
+            self.functions.insert(native.name.clone(), signature);
+        }
+
+        // signatures first, so functions can call each other in any order
+        for id in arena.item_ids() {
+            let result = self.declare_item(arena, id);
+            self.locate(result, arena[id].span)?;
+        }
+        self.check_main()?;
+
+        for id in arena.item_ids() {
+            let result = self.check_item(arena, &mut types, id);
+            self.locate(result, arena[id].span)?;
+        }
+
+        Ok(types)
+    }
+
+    /// Remember the span of the first error, inner nodes locate it before outer ones
+    fn locate<T>(&mut self, result: Result<T>, span: Span) -> Result<T> {
+        if result.is_err() {
+            self.error_span.get_or_insert(span);
+        }
+        result
+    }
+
+    /// Code after a diverging statement still compiles, it just never runs
+    fn warn_unreachable(&mut self, what: &str, span: Span, diverging: Span) {
+        let warning = Diagnostic::warning(format!("unreachable {what}"))
+            .with_primary(span, format!("unreachable {what}"))
+            .with_secondary(diverging, "any code following this is unreachable");
+        self.warnings.push(warning);
+    }
+
+    fn resolve_type(&mut self, arena: &Arena, id: ty::Id) -> Result<Type> {
+        let result = self.resolve_type_kind(arena, id);
+        self.locate(result, arena[id].span)
+    }
+
+    fn resolve_type_kind(&mut self, arena: &Arena, id: ty::Id) -> Result<Type> {
+        let ty = match &arena[id].kind {
+            ty::Kind::Named(name) => match name.as_str() {
+                "i64" => Type::Int64,
+                "u64" => Type::Uint64,
+                "f64" => Type::Float64,
+                "bool" => Type::Bool,
+                "str" => Type::Str,
+                _ if self.structs.contains_key(name) => Type::Struct(name.clone()),
+                _ if self.enums.contains_key(name) => Type::Enum(name.clone()),
+                _ => return Err(Error::UndefinedType(name.to_owned())),
+            },
+            ty::Kind::Tuple(elements) if elements.is_empty() => Type::Unit,
+            ty::Kind::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|el| self.resolve_type(arena, *el))
+                    .collect::<Result<_>>()?;
+                Type::Tuple(elements)
+            }
+            // syntax errors are reported by the parser, `!` fits anywhere
+            ty::Kind::Never | ty::Kind::Error => Type::Never,
+            ty::Kind::Array(element) => Type::Array(Box::new(self.resolve_type(arena, *element)?)),
+            ty::Kind::Function { params, ret } => {
+                let params = params
+                    .iter()
+                    .map(|param| self.resolve_type(arena, *param))
+                    .collect::<Result<_>>()?;
+                let ret = match ret {
+                    Some(ret) => self.resolve_type(arena, *ret)?,
+                    None => Type::Unit,
+                };
+                Type::Fn(params, Box::new(ret))
+            }
+        };
+
+        Ok(ty)
+    }
+
+    fn declare_item(&mut self, arena: &Arena, id: item::Id) -> Result<()> {
+        match &arena[id].kind {
+            item::Kind::Function {
+                name, params, ret, ..
+            } => {
+                let params = params
+                    .iter()
+                    .map(|param| self.resolve_type(arena, param.ty))
+                    .collect::<Result<_>>()?;
+                let ret = match ret {
+                    Some(ret) => self.resolve_type(arena, *ret)?,
+                    None => Type::Unit,
+                };
+
+                self.check_unique(name)?;
+                let signature = Signature { params, ret };
+                self.functions.insert(name.clone(), signature);
+            }
+            item::Kind::Const { name, ty, init } => {
+                self.check_unique(name)?;
+                let ty = self.resolve_type(arena, *ty)?;
+                self.consts.insert(name.clone(), (ty, *init));
+            }
+            item::Kind::Struct { name, fields } => {
+                let mut resolved = Vec::<(String, Type)>::new();
+                for field in fields {
+                    if resolved.iter().any(|(name, _)| *name == field.name) {
+                        return Err(Error::DuplicateField(field.name.clone()));
+                    }
+                    let ty = self.resolve_type(arena, field.ty)?;
+                    resolved.push((field.name.clone(), ty));
+                }
+                self.structs.insert(name.clone(), resolved);
+            }
+            item::Kind::Enum { name, variants } => {
+                let mut resolved = Variants::new();
+                for variant in variants {
+                    if resolved.iter().any(|(name, _)| *name == variant.name) {
+                        return Err(Error::DuplicateDefinition(variant.name.clone()));
+                    }
+                    let fields = variant
+                        .fields
+                        .iter()
+                        .map(|field| self.resolve_type(arena, *field))
+                        .collect::<Result<_>>()?;
+                    resolved.push((variant.name.clone(), fields));
+                }
+                self.enums.insert(name.clone(), resolved);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Functions, constants and built-ins share one namespace
+    fn check_unique(&self, name: &str) -> Result<()> {
+        let defined = self.functions.contains_key(name) || self.consts.contains_key(name);
+        match defined || BUILTINS.contains(&name) {
+            true => Err(Error::DuplicateDefinition(name.to_owned())),
+            false => Ok(()),
+        }
+    }
+
+    /// Structs and enums share one namespace
+    fn check_unique_type(&self, name: &str) -> Result<()> {
+        match self.structs.contains_key(name) || self.enums.contains_key(name) {
+            true => Err(Error::DuplicateDefinition(name.to_owned())),
+            false => Ok(()),
+        }
+    }
+
+    /// Entry point takes nothing and returns an exit code or nothing
+    fn check_main(&self) -> Result<()> {
+        let main = self.functions.get("main").ok_or(Error::MissingMain)?;
+        match (main.params.is_empty(), &main.ret) {
+            (true, Type::Int64 | Type::Unit | Type::Never) => Ok(()),
+            _ => Err(Error::InvalidMain),
+        }
+    }
+
+    fn check_item(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        id: item::Id,
+    ) -> Result<()> {
+        match &arena[id].kind {
+            item::Kind::Function {
+                name, params, body, ..
+            } => {
+                let signature = self.functions[name].clone();
+                self.ret = signature.ret.clone();
+
+                self.begin_scope();
+                for (param, ty) in params.iter().zip(signature.params) {
+                    self.declare(&param.name, ty);
+                }
+                let body_ty = self.calculate_expression_type(arena, types, *body)?;
+                self.end_scope();
+
+                Self::expect(&signature.ret, &body_ty)?;
+            }
+            item::Kind::Const { name, .. } => {
+                self.fold_const(arena, types, name)?;
+            }
+            item::Kind::Struct { .. } | item::Kind::Enum { .. } => (),
+        }
+
+        Ok(())
+    }
+
+    /// Type check and evaluate a constant, folding the ones it refers to first
+    fn fold_const(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        name: &str,
+    ) -> Result<Value> {
+        match self.values.get(name) {
+            Some(Some(value)) => return Ok(*value),
+            Some(None) => return Err(Error::CyclicConstant(name.to_owned())),
+            None => (),
+        }
+
+        let (ty, init) = self.consts[name].clone();
+        self.values.insert(name.to_owned(), None);
+
+        let init_ty = self.calculate_expression_type(arena, types, init)?;
+        Self::expect(&ty, &init_ty)?;
+        let value =
+            self.evaluate(arena, types, init)?
+                .map_err(|reason| Error::ConstEvaluation {
+                    name: name.to_owned(),
+                    reason,
+                })?;
+
+        self.values.insert(name.to_owned(), Some(value));
+        Ok(value)
+    }
+
+    /// Evaluate a typechecked constant expression
+    ///
+    /// The outer error rejects non-constant expressions, the inner one
+    /// carries a failed evaluation like overflow
+    fn evaluate(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        id: expression::Id,
+    ) -> Result<std::result::Result<Value, &'static str>> {
+        use expression::Kind::*;
+
+        let value = match &arena[id].kind {
+            Int64(i) => Value::Int64(*i),
+            Uint64(u) => Value::Uint64(*u),
+            Float64(f) => Value::Float64(*f),
+            Bool(b) => Value::Bool(*b),
+            Unit => Value::Unit,
+            Identifier(name) if self.consts.contains_key(name) => {
+                self.fold_const(arena, types, name)?
+            }
+            Prefix { op, exp } => {
+                let value = self.evaluate(arena, types, *exp)?;
+                return Ok(value.and_then(|value| fold_prefix(*op, value)));
+            }
+            Cast { exp, .. } => {
+                let value = self.evaluate(arena, types, *exp)?;
+                return Ok(value.map(|value| fold_cast(value, &types[&id])));
+            }
+            Infix { lhs, rhs, op } if !op.is_assignment() => {
+                let lhs = self.evaluate(arena, types, *lhs)?;
+                let rhs = self.evaluate(arena, types, *rhs)?;
+                return Ok(lhs.and_then(|lhs| fold_infix(*op, lhs, rhs?)));
+            }
+            _ => return Err(super::Error::NotConstant),
+        };
+
+        Ok(Ok(value))
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &str, ty: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), ty);
+        }
+    }
+
+    /// Innermost local with the name, and the index of the scope declaring it
+    fn find_local(&self, name: &str) -> Option<(usize, Type)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| Some((depth, scope.get(name)?.clone())))
+    }
+
+    fn lookup_local(&self, name: &str) -> Option<Type> {
+        self.find_local(name).map(|(_, ty)| ty)
+    }
+
+    /// Locals shadow global constants, which shadow functions
+    fn lookup(&self, name: &str) -> Result<Type> {
+        if let Some(ty) = self.lookup_local(name) {
+            return Ok(ty);
+        }
+        if let Some((ty, _)) = self.consts.get(name) {
+            return Ok(ty.clone());
+        }
+        match self.functions.get(name) {
+            Some(signature) => Ok(Type::Fn(
+                signature.params.clone(),
+                Box::new(signature.ret.clone()),
+            )),
+            None => Err(Error::UndefinedVariable(name.to_owned())),
+        }
+    }
+
+    /// Whether the name is a local declared outside of the innermost lambda
+    fn is_captured(&self, name: &str) -> bool {
+        match (self.find_local(name), self.closures.last()) {
+            (Some((depth, _)), Some(closure)) => depth < closure.depth,
+            _ => false,
+        }
+    }
+
+    /// Record a use of a local in every enclosing lambda it is declared outside of
+    fn capture(&mut self, name: &str) {
+        let Some((depth, _)) = self.find_local(name) else {
+            return;
+        };
+        for closure in &mut self.closures {
+            if closure.depth > depth && !closure.captures.iter().any(|known| known == name) {
+                closure.captures.push(name.to_owned());
+            }
+        }
+    }
+
+    /// `break` and `continue` need an enclosing loop with a matching label
+    fn check_loop_jump(&self, keyword: &'static str, label: &Option<String>) -> Result<()> {
+        match label {
+            Some(label) if !self.loops.iter().any(|l| l.as_ref() == Some(label)) => {
+                Err(Error::UndefinedLabel(label.clone()))
+            }
+            None if self.loops.is_empty() => Err(Error::OutsideLoop(keyword)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether the statement diverges
     fn check_statement(
-        &self,
+        &mut self,
         arena: &Arena,
         types: &mut HashMap<expression::Id, Type>,
         id: statement::Id,
-    ) -> Result<()> {
+    ) -> Result<bool> {
+        let result = self.check_statement_kind(arena, types, id);
+        self.locate(result, arena[id].span)
+    }
+
+    fn check_statement_kind(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        id: statement::Id,
+    ) -> Result<bool> {
         use statement::Kind::*;
 
         let kind = &arena[id].kind;
-        match kind {
+        let diverges = match kind {
             Expression(exp) => {
-                self.calculate_expression_type(arena, types, *exp)?;
+                let ty = self.calculate_expression_type(arena, types, *exp)?;
+                ty == Type::Never
             }
-            Block(stmts) => {
-                for stmt in stmts {
-                    self.check_statement(arena, types, *stmt)?;
+            Let { pattern, ty, init } => {
+                let init_ty = self.calculate_expression_type(arena, types, *init)?;
+                let diverges = init_ty == Type::Never;
+                let ty = match ty {
+                    Some(ty) => {
+                        let ty = self.resolve_type(arena, *ty)?;
+                        Self::expect(&ty, &init_ty)?;
+                        ty
+                    }
+                    None => {
+                        let result = Self::check_inferred(&init_ty);
+                        self.locate(result, arena[*init].span)?;
+                        init_ty
+                    }
+                };
+                self.check_unique_bindings(arena, *pattern, &mut Vec::new())?;
+                self.bind_pattern(arena, types, *pattern, ty.clone())?;
+                if !diverges
+                    && let Some(missing) = Arms::new(&self.enums, arena, &[*pattern], ty).missing()
+                {
+                    return Err(Error::RefutablePattern(missing));
                 }
+                diverges
             }
-            _ => unimplemented!(),
         };
 
+        Ok(diverges)
+    }
+
+    /// Rejects `let (a, a) = ..` and `Enum::A(a, a) => ..`
+    fn check_unique_bindings<'a>(
+        &self,
+        arena: &'a Arena,
+        id: pattern::Id,
+        names: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        match &arena[id].kind {
+            pattern::Kind::Identifier(name) if names.contains(&name.as_str()) => {
+                return Err(Error::DuplicateBinding(name.clone()));
+            }
+            pattern::Kind::Identifier(name) => names.push(name),
+            pattern::Kind::Wildcard | pattern::Kind::Literal(_) | pattern::Kind::Error => (),
+            pattern::Kind::Tuple(elements)
+            | pattern::Kind::Variant {
+                fields: elements, ..
+            } => {
+                for element in elements {
+                    self.check_unique_bindings(arena, *element, names)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Declare the names of a pattern, destructuring tuples and variants element by element
+    fn bind_pattern(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        id: pattern::Id,
+        ty: Type,
+    ) -> Result<()> {
+        match (&arena[id].kind, ty) {
+            (pattern::Kind::Identifier(name), ty) => self.declare(name, ty),
+            (pattern::Kind::Wildcard | pattern::Kind::Error, _) => (),
+            (pattern::Kind::Literal(literal), ty) => {
+                let literal_ty = self.calculate_expression_type(arena, types, *literal)?;
+                Self::unify(ty, literal_ty)?;
+            }
+            (
+                pattern::Kind::Variant {
+                    enum_name,
+                    variant,
+                    fields,
+                },
+                ty,
+            ) => {
+                Self::unify(ty, Type::Enum(enum_name.clone()))?;
+                let payload = self.variant_fields(enum_name, variant, fields.len())?;
+                for (field, ty) in fields.iter().zip(payload) {
+                    self.bind_pattern(arena, types, *field, ty)?;
+                }
+            }
+            (pattern::Kind::Tuple(elements), Type::Never) => {
+                for element in elements {
+                    self.bind_pattern(arena, types, *element, Type::Never)?;
+                }
+            }
+            (pattern::Kind::Tuple(elements), Type::Unit) if elements.is_empty() => (),
+            (pattern::Kind::Tuple(elements), Type::Tuple(tys)) if elements.len() == tys.len() => {
+                for (element, ty) in elements.iter().zip(tys) {
+                    self.bind_pattern(arena, types, *element, ty)?;
+                }
+            }
+            (pattern::Kind::Tuple(elements), found) => {
+                return Err(Error::PatternMismatch {
+                    arity: elements.len(),
+                    found,
+                });
+            }
+        }
+
         Ok(())
     }
 
     fn calculate_expression_type(
-        &self,
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        id: expression::Id,
+    ) -> Result<Type> {
+        let result = self.calculate_expression_kind_type(arena, types, id);
+        self.locate(result, arena[id].span)
+    }
+
+    fn calculate_expression_kind_type(
+        &mut self,
         arena: &Arena,
         types: &mut HashMap<expression::Id, Type>,
         id: expression::Id,
@@ -58,30 +611,1209 @@ impl<'e> Typer<'e> {
             expression::Kind::Uint64(_) => Type::Uint64,
             expression::Kind::Float64(_) => Type::Float64,
             expression::Kind::Bool(_) => Type::Bool,
+            expression::Kind::Str(_) => Type::Str,
             expression::Kind::Unit => Type::Unit,
+            expression::Kind::Error => Type::Never,
+            expression::Kind::Identifier(name) => {
+                self.capture(name);
+                self.lookup(name)?
+            }
+            expression::Kind::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|el| self.calculate_expression_type(arena, types, *el))
+                    .collect::<Result<_>>()?;
+                Type::Tuple(elements)
+            }
+            expression::Kind::StructLiteral { name, fields } => {
+                self.check_struct_literal(arena, types, name, fields)?
+            }
+            expression::Kind::Field { object, name } => {
+                let ty = self.calculate_expression_type(arena, types, *object)?;
+                self.field_type(ty, name)?
+            }
+            expression::Kind::Variant {
+                enum_name,
+                variant,
+                args,
+            } => {
+                let payload = self.variant_fields(enum_name, variant, args.len())?;
+                for (arg, ty) in args.iter().zip(&payload) {
+                    let arg_ty = self.calculate_expression_type(arena, types, *arg)?;
+                    Self::expect(ty, &arg_ty)?;
+                }
+                Type::Enum(enum_name.clone())
+            }
+            expression::Kind::Array(elements) => {
+                let mut element_ty = Type::Never;
+                for element in elements {
+                    let ty = self.calculate_expression_type(arena, types, *element)?;
+                    element_ty = Self::unify(element_ty, ty)?;
+                }
+                Type::Array(Box::new(element_ty))
+            }
+            expression::Kind::Index { array, index } => {
+                let ty = self.calculate_expression_type(arena, types, *array)?;
+                let index_ty = self.calculate_expression_type(arena, types, *index)?;
+                Self::expect(&Type::Int64, &index_ty)?;
+                match ty {
+                    Type::Array(element) => *element,
+                    ty => return Err(Error::NotIndexable(ty)),
+                }
+            }
+            expression::Kind::TupleIndex { tuple, index } => {
+                let ty = self.calculate_expression_type(arena, types, *tuple)?;
+                match ty {
+                    Type::Tuple(mut elements) if *index < elements.len() => {
+                        elements.swap_remove(*index)
+                    }
+                    ty => {
+                        return Err(Error::UnknownField {
+                            ty,
+                            field: index.to_string(),
+                        });
+                    }
+                }
+            }
+            expression::Kind::Infix { lhs, rhs, op } if op.is_assignment() => {
+                self.check_assignment(arena, types, *op, *lhs, *rhs)?
+            }
             expression::Kind::Infix { lhs, rhs, op } => {
                 let lty = self.calculate_expression_type(arena, types, *lhs)?;
                 let rty = self.calculate_expression_type(arena, types, *rhs)?;
-                self.env.resolve_infix(*op, lty, rty)?
+                self.resolve_infix(arena, *op, (*lhs, lty), (*rhs, rty))?
             }
             expression::Kind::Prefix { op, exp } => {
                 let ty = self.calculate_expression_type(arena, types, *exp)?;
-                self.env.resolve_prefix(*op, ty)?
+                self.resolve_prefix(arena, *op, *exp, ty)?
+            }
+            expression::Kind::Cast { exp, ty } => {
+                let from = self.calculate_expression_type(arena, types, *exp)?;
+                let to = self.resolve_type(arena, *ty)?;
+                self.env.resolve_cast(from, to)?
+            }
+            expression::Kind::Block { stmts, tail } => {
+                self.begin_scope();
+                // statement the rest of the block comes after, reported once per block
+                let mut diverging = None;
+                let mut reported = false;
+                for stmt in stmts {
+                    if let (Some(diverging), false) = (diverging, reported) {
+                        self.warn_unreachable("statement", arena[*stmt].span, diverging);
+                        reported = true;
+                    }
+                    if self.check_statement(arena, types, *stmt)? {
+                        diverging = diverging.or(Some(arena[*stmt].span));
+                    }
+                }
+                let ty = match tail {
+                    Some(tail) => {
+                        let ty = self.calculate_expression_type(arena, types, *tail)?;
+                        match diverging {
+                            Some(diverging) if !reported => {
+                                self.warn_unreachable("expression", arena[*tail].span, diverging);
+                                Type::Never
+                            }
+                            Some(_) => Type::Never,
+                            None => ty,
+                        }
+                    }
+                    None if diverging.is_some() => Type::Never,
+                    None => Type::Unit,
+                };
+                self.end_scope();
+                ty
+            }
+            expression::Kind::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                let cty = self.calculate_expression_type(arena, types, *cond)?;
+                Self::expect(&Type::Bool, &cty)?;
+
+                let then_ty = self.calculate_expression_type(arena, types, *then)?;
+                match otherwise {
+                    Some(otherwise) => {
+                        let else_ty = self.calculate_expression_type(arena, types, *otherwise)?;
+                        Self::unify(then_ty, else_ty)?
+                    }
+                    // without `else` the missing branch evaluates to `()`
+                    None => {
+                        Self::expect(&Type::Unit, &then_ty)?;
+                        Type::Unit
+                    }
+                }
+            }
+            expression::Kind::While { label, cond, body } => {
+                let cty = self.calculate_expression_type(arena, types, *cond)?;
+                Self::expect(&Type::Bool, &cty)?;
+
+                self.loops.push(label.clone());
+                let body_ty = self.calculate_expression_type(arena, types, *body);
+                self.loops.pop();
+                Self::expect(&Type::Unit, &body_ty?)?;
+
+                Type::Unit
+            }
+            expression::Kind::Match { scrutinee, arms } => {
+                self.check_match(arena, types, *scrutinee, arms)?
+            }
+            expression::Kind::Break { label } => {
+                self.check_loop_jump("break", label)?;
+                Type::Never
+            }
+            expression::Kind::Continue { label } => {
+                self.check_loop_jump("continue", label)?;
+                Type::Never
+            }
+            expression::Kind::Call { callee, args } => {
+                self.check_call(arena, types, *callee, args)?
+            }
+            expression::Kind::Lambda { params, ret, body } => {
+                self.check_lambda(arena, types, id, params, *ret, *body)?
+            }
+            expression::Kind::Return(value) => {
+                let ty = match value {
+                    Some(value) => self.calculate_expression_type(arena, types, *value)?,
+                    None => Type::Unit,
+                };
+                match self.infer_ret {
+                    true => self.ret = Self::unify(self.ret.clone(), ty)?,
+                    false => Self::expect(&self.ret, &ty)?,
+                }
+                Type::Never
             }
         };
 
-        types.insert(id, ty);
-        dbg!(ty);
+        types.insert(id, ty.clone());
         Ok(ty)
     }
+
+    /// Every field is initialized exactly once with a value of its type
+    fn check_struct_literal(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        name: &str,
+        fields: &[(String, expression::Id)],
+    ) -> Result<Type> {
+        let declared = self
+            .structs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::UndefinedType(name.to_owned()))?;
+
+        let mut initialized = Vec::new();
+        for (field, value) in fields {
+            if initialized.contains(&field) {
+                return Err(Error::DuplicateField(field.clone()));
+            }
+            initialized.push(field);
+
+            let ty = self.field_type(Type::Struct(name.to_owned()), field)?;
+            let value_ty = self.calculate_expression_type(arena, types, *value)?;
+            Self::expect(&ty, &value_ty)?;
+        }
+
+        let missing = declared
+            .iter()
+            .filter(|(field, _)| !initialized.contains(&field))
+            .map(|(field, _)| format!("`{field}`"))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(Error::MissingFields {
+                name: name.to_owned(),
+                fields: missing,
+            });
+        }
+
+        Ok(Type::Struct(name.to_owned()))
+    }
+
+    /// Payload types of a variant, which must take `count` values
+    fn variant_fields(&self, enum_name: &str, variant: &str, count: usize) -> Result<Vec<Type>> {
+        let variants = self
+            .enums
+            .get(enum_name)
+            .ok_or_else(|| Error::UndefinedType(enum_name.to_owned()))?;
+        let Some((_, fields)) = variants.iter().find(|(name, _)| name == variant) else {
+            return Err(Error::UnknownVariant {
+                name: enum_name.to_owned(),
+                variant: variant.to_owned(),
+            });
+        };
+        if fields.len() != count {
+            return Err(Error::VariantArity {
+                variant: variant.to_owned(),
+                expected: fields.len(),
+                found: count,
+            });
+        }
+        Ok(fields.clone())
+    }
+
+    /// Arms bind their patterns in their own scope and agree on one type
+    ///
+    /// Together they must cover every value of the scrutinee, and each arm
+    /// must match something the arms above it do not
+    fn check_match(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        scrutinee: expression::Id,
+        arms: &[expression::Arm],
+    ) -> Result<Type> {
+        let scrutinee_ty = self.calculate_expression_type(arena, types, scrutinee)?;
+        let result = Self::check_inferred(&scrutinee_ty);
+        self.locate(result, arena[scrutinee].span)?;
+
+        let mut ty = Type::Never;
+        for arm in arms {
+            self.begin_scope();
+            self.check_unique_bindings(arena, arm.pattern, &mut Vec::new())?;
+            self.bind_pattern(arena, types, arm.pattern, scrutinee_ty.clone())?;
+            let body_ty = self.calculate_expression_type(arena, types, arm.body)?;
+            self.end_scope();
+            ty = Self::unify(ty, body_ty)?;
+        }
+
+        // nothing is matched against a diverging scrutinee
+        if scrutinee_ty == Type::Never {
+            return Ok(ty);
+        }
+
+        let patterns = arms.iter().map(|arm| arm.pattern).collect::<Vec<_>>();
+        let arms = Arms::new(&self.enums, arena, &patterns, scrutinee_ty);
+        if let Some(arm) = arms.unreachable() {
+            return Err(Error::UnreachableArm(arm));
+        }
+        if let Some(missing) = arms.missing() {
+            return Err(Error::NonExhaustive(missing));
+        }
+
+        Ok(ty)
+    }
+
+    /// Type of a named field, listing the available ones when it is missing
+    fn field_type(&self, ty: Type, field: &str) -> Result<Type> {
+        let Type::Struct(name) = ty else {
+            return Err(Error::UnknownField {
+                ty,
+                field: field.to_owned(),
+            });
+        };
+        let fields = &self.structs[&name];
+        match fields.iter().find(|(known, _)| known == field) {
+            Some((_, ty)) => Ok(ty.clone()),
+            None => Err(Error::UnknownStructField {
+                field: field.to_owned(),
+                available: fields
+                    .iter()
+                    .map(|(known, _)| format!("`{known}`"))
+                    .collect(),
+                name,
+            }),
+        }
+    }
+
+    /// Lambda body is checked in its own scope with its own return type
+    ///
+    /// Locals of the enclosing functions it uses are captured
+    fn check_lambda(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        id: expression::Id,
+        params: &[item::Param],
+        ret: Option<ty::Id>,
+        body: expression::Id,
+    ) -> Result<Type> {
+        let params = params
+            .iter()
+            .map(|param| Ok((param.name.as_str(), self.resolve_type(arena, param.ty)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let declared = ret.map(|ret| self.resolve_type(arena, ret)).transpose()?;
+
+        let outer_ret = std::mem::replace(&mut self.ret, declared.clone().unwrap_or(Type::Never));
+        let outer_infer_ret = std::mem::replace(&mut self.infer_ret, declared.is_none());
+        // loops outside the lambda are not targets of `break` and `continue`
+        let outer_loops = std::mem::take(&mut self.loops);
+        self.closures.push(Closure {
+            depth: self.scopes.len(),
+            captures: Vec::new(),
+        });
+
+        self.begin_scope();
+        for (name, ty) in &params {
+            self.declare(name, ty.clone());
+        }
+        let body_ty = self.calculate_expression_type(arena, types, body);
+        self.end_scope();
+
+        let closure = self.closures.pop().expect("pushed above");
+        self.loops = outer_loops;
+        self.infer_ret = outer_infer_ret;
+        let returned = std::mem::replace(&mut self.ret, outer_ret);
+
+        let ret = match declared {
+            Some(declared) => {
+                Self::expect(&declared, &body_ty?)?;
+                declared
+            }
+            None => Self::unify(returned, body_ty?)?,
+        };
+        self.captures.insert(id, closure.captures);
+
+        let params = params.into_iter().map(|(_, ty)| ty).collect();
+        Ok(Type::Fn(params, Box::new(ret)))
+    }
+
+    /// Calls name a function directly or call a function value,
+    /// arguments must match its parameters
+    fn check_call(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        callee: expression::Id,
+        args: &[expression::Id],
+    ) -> Result<Type> {
+        let signature = match &arena[callee].kind {
+            expression::Kind::Identifier(name)
+                if self.lookup_local(name).is_none() && !self.consts.contains_key(name) =>
+            {
+                match self.functions.get(name).cloned() {
+                    Some(signature) => signature,
+                    None => return self.check_builtin(arena, types, name, args),
+                }
+            }
+            _ => match self.calculate_expression_type(arena, types, callee)? {
+                Type::Fn(params, ret) => Signature { params, ret: *ret },
+                ty => return Err(Error::NotCallable(ty)),
+            },
+        };
+
+        if signature.params.len() != args.len() {
+            return Err(Error::ArityMismatch {
+                expected: signature.params.len(),
+                found: args.len(),
+            });
+        }
+
+        for (param, arg) in signature.params.iter().zip(args) {
+            let ty = self.calculate_expression_type(arena, types, *arg)?;
+            Self::expect(param, &ty)?;
+        }
+
+        Ok(signature.ret)
+    }
+
+    /// Built-ins accept arguments of several types
+    fn check_builtin(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        name: &str,
+        args: &[expression::Id],
+    ) -> Result<Type> {
+        let Some(builtin) = BUILTINS.into_iter().find(|builtin| *builtin == name) else {
+            return Err(Error::UndefinedFunction(name.to_owned()));
+        };
+        let args = args
+            .iter()
+            .map(|arg| self.calculate_expression_type(arena, types, *arg))
+            .collect::<Result<Vec<_>>>()?;
+
+        let ty = match (builtin, args.as_slice()) {
+            ("panic", []) => Type::Never,
+            ("println", [Type::Str]) => Type::Unit,
+            ("len", [Type::Str | Type::Array(_)]) => Type::Int64,
+            ("push", [Type::Array(element), value]) => {
+                Self::expect(element, value)?;
+                Type::Unit
+            }
+            ("pop", [Type::Array(element)]) => *element.clone(),
+            _ => return Err(Error::InvalidBuiltinCall(builtin)),
+        };
+
+        Ok(ty)
+    }
+
+    /// `found` must be `expected`, or coerce to it
+    fn expect(expected: &Type, found: &Type) -> Result<()> {
+        match Self::coerces(found, expected) {
+            true => Ok(()),
+            false => Err(Error::Mismatch {
+                expected: expected.clone(),
+                found: found.clone(),
+            }),
+        }
+    }
+
+    /// `!` coerces to any type, also inside arrays and tuples like `[]: [!]`
+    fn coerces(found: &Type, expected: &Type) -> bool {
+        match (found, expected) {
+            (Type::Never, _) => true,
+            (Type::Array(found), Type::Array(expected)) => Self::coerces(found, expected),
+            (Type::Tuple(found), Type::Tuple(expected)) => {
+                found.len() == expected.len()
+                    && found.iter().zip(expected).all(|(f, e)| Self::coerces(f, e))
+            }
+            (found, expected) => found == expected,
+        }
+    }
+
+    /// Names may not be bound to an array of unknown elements
+    ///
+    /// `[!]` coerces to any array type, which is only sound for a literal
+    /// that nothing else refers to, a named one could be used as two types
+    fn check_inferred(ty: &Type) -> Result<()> {
+        fn unknown(ty: &Type) -> bool {
+            match ty {
+                Type::Array(element) => **element == Type::Never || unknown(element),
+                Type::Tuple(elements) => elements.iter().any(unknown),
+                _ => false,
+            }
+        }
+        match unknown(ty) {
+            true => Err(Error::AnnotationNeeded(ty.clone())),
+            false => Ok(()),
+        }
+    }
+
+    /// Common type of two branches, a diverging branch takes the other's type
+    fn unify(lhs: Type, rhs: Type) -> Result<Type> {
+        match (lhs, rhs) {
+            (lhs, rhs) if Self::coerces(&lhs, &rhs) => Ok(rhs),
+            (lhs, rhs) if Self::coerces(&rhs, &lhs) => Ok(lhs),
+            (expected, found) => Err(Error::Mismatch { expected, found }),
+        }
+    }
+
+    /// Assignment evaluates to `()`, compound forms must keep the target type
+    ///
+    /// Targets are locals, array elements and struct fields
+    fn check_assignment(
+        &mut self,
+        arena: &Arena,
+        types: &mut HashMap<expression::Id, Type>,
+        op: operator::Infix,
+        target: expression::Id,
+        value: expression::Id,
+    ) -> Result<Type> {
+        match &arena[target].kind {
+            expression::Kind::Identifier(name)
+                if self.lookup_local(name).is_none() && self.consts.contains_key(name) =>
+            {
+                return Err(Error::AssignToConstant(name.clone()));
+            }
+            expression::Kind::Identifier(name) if self.is_captured(name) => {
+                return Err(Error::AssignToCapture(name.clone()));
+            }
+            expression::Kind::Identifier(_)
+            | expression::Kind::Index { .. }
+            | expression::Kind::Field { .. } => (),
+            _ => return Err(Error::InvalidAssignmentTarget),
+        }
+
+        let tty = self.calculate_expression_type(arena, types, target)?;
+        let vty = self.calculate_expression_type(arena, types, value)?;
+
+        let found = match op.compound_op() {
+            Some(op) => self.resolve_infix(arena, op, (target, tty.clone()), (value, vty))?,
+            None => vty,
+        };
+
+        Self::expect(&tty, &found)?;
+        Ok(Type::Unit)
+    }
+}
+
+impl Typer<'_> {
+    /// Result type of an infix operator, or an error naming what it accepts
+    fn resolve_infix(
+        &self,
+        arena: &Arena,
+        op: operator::Infix,
+        (lhs, lty): (expression::Id, Type),
+        (rhs, rty): (expression::Id, Type),
+    ) -> Result<Type> {
+        if let Some(ty) = self.env.infix.get(&(op, lty.clone(), rty.clone())) {
+            return Ok(ty.clone());
+        }
+        Err(Error::InvalidInfix(Box::new(InfixMismatch {
+            op: op.symbol(),
+            fix: self.infix_fix(arena, op, (lhs, &lty), (rhs, &rty)),
+            lhs: lty,
+            rhs: rty,
+            lhs_span: arena[lhs].span,
+            rhs_span: arena[rhs].span,
+            accepted: self.env.accepted_infix(op),
+        })))
+    }
+
+    /// Way to make mixed numeric operands match, preferring to change a literal
+    fn infix_fix(
+        &self,
+        arena: &Arena,
+        op: operator::Infix,
+        (lhs, lty): (expression::Id, &Type),
+        (rhs, rty): (expression::Id, &Type),
+    ) -> Option<String> {
+        let numeric = |ty: &Type| matches!(ty, Type::Int64 | Type::Uint64 | Type::Float64);
+        let accepts = |ty: &Type| self.env.infix.contains_key(&(op, ty.clone(), ty.clone()));
+        if lty == rty || !numeric(lty) || !numeric(rty) {
+            return None;
+        }
+
+        for (literal, other) in [(rhs, lty), (lhs, rty)] {
+            if !accepts(other) {
+                continue;
+            }
+            let fix = match (&arena[literal].kind, other) {
+                (expression::Kind::Int64(i), Type::Uint64) => {
+                    format!("use a `u64` literal: `{i}u64`")
+                }
+                (expression::Kind::Int64(i), Type::Float64) => {
+                    format!("use a float literal: `{i}.0`")
+                }
+                (expression::Kind::Uint64(u), Type::Int64) => {
+                    format!("use an `i64` literal without suffix: `{u}`")
+                }
+                _ => continue,
+            };
+            return Some(fix);
+        }
+
+        if accepts(lty) {
+            Some(format!("convert the right operand with `as {lty}`"))
+        } else if accepts(rty) {
+            Some(format!("convert the left operand with `as {rty}`"))
+        } else {
+            None
+        }
+    }
+
+    /// Result type of a prefix operator, or an error naming what it accepts
+    fn resolve_prefix(
+        &self,
+        arena: &Arena,
+        op: operator::Prefix,
+        exp: expression::Id,
+        ty: Type,
+    ) -> Result<Type> {
+        if let Some(ty) = self.env.prefix.get(&(op, ty.clone())) {
+            return Ok(ty.clone());
+        }
+
+        let accepted = self.env.accepted_prefix(op);
+        let numeric = |ty: &Type| matches!(ty, Type::Int64 | Type::Uint64 | Type::Float64);
+        let fix = if op == operator::Prefix::Negate
+            && self
+                .env
+                .prefix
+                .contains_key(&(operator::Prefix::BitNot, ty.clone()))
+        {
+            Some("use `~` for the bitwise complement of an integer".to_owned())
+        } else if numeric(&ty) {
+            accepted
+                .iter()
+                .find(|accepted| numeric(accepted))
+                .map(|accepted| format!("convert the operand with `as {accepted}`"))
+        } else {
+            None
+        };
+        Err(Error::InvalidPrefix(Box::new(PrefixMismatch {
+            op: op.symbol(),
+            operand: ty,
+            operand_span: arena[exp].span,
+            accepted,
+            fix,
+        })))
+    }
 }
 
 impl TypeEnv {
-    fn resolve_infix(&self, op: operator::Infix, lty: Type, rty: Type) -> Result<Type> {
-        self.infix.get(&(op, lty, rty)).copied().ok_or(Error {})
+    /// Operand types the operator is defined for, sorted by name
+    fn accepted_infix(&self, op: operator::Infix) -> Vec<(Type, Type)> {
+        let mut accepted = self
+            .infix
+            .keys()
+            .filter(|(infix, ..)| *infix == op)
+            .map(|(_, lhs, rhs)| (lhs.clone(), rhs.clone()))
+            .collect::<Vec<_>>();
+        accepted.sort_by_key(|(lhs, rhs)| (lhs.to_string(), rhs.to_string()));
+        accepted
+    }
+
+    fn accepted_prefix(&self, op: operator::Prefix) -> Vec<Type> {
+        let mut accepted = self
+            .prefix
+            .keys()
+            .filter(|(prefix, _)| *prefix == op)
+            .map(|(_, operand)| operand.clone())
+            .collect::<Vec<_>>();
+        accepted.sort_by_key(|operand| operand.to_string());
+        accepted
+    }
+
+    fn resolve_cast(&self, from: Type, to: Type) -> Result<Type> {
+        match self.casts.contains(&(from.clone(), to.clone())) {
+            true => Ok(to),
+            false => Err(Error::InvalidCast { from, to }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Result<Module> {
+        let syntax = Parser::new(Lexer::new(source))
+            .parse()
+            .expect("should parse");
+        let env = TypeEnv::new();
+        Typer::new(&env).check(syntax).map_err(|error| error.error)
+    }
+
+    #[test]
+    fn let_binding_takes_initializer_type() {
+        let module = check("fn main() -> i64 { let a = 1; a * 2 }").expect("should typecheck");
+        assert!(module.types.values().all(|ty| *ty == Type::Int64));
+    }
+
+    #[test]
+    fn assignment_requires_matching_types() {
+        assert!(check("fn main() { let a = 1; a = 2; a += 3; }").is_ok());
+        let result = check("fn main() { let a = 1; a = 2.0; }");
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Int64,
+                found: Type::Float64
+            })
+        ));
+    }
+
+    #[test]
+    fn assignment_target_must_be_a_place() {
+        let result = check("fn main() { let a = 1; a + 1 = 2; }");
+        assert!(matches!(result, Err(Error::InvalidAssignmentTarget)));
+    }
+
+    #[test]
+    fn if_branches_must_agree() {
+        assert!(check("fn main() { let a = if true { 1 } else { 2 }; }").is_ok());
+        assert!(check("fn main() { if 1 < 2 { 1; } }").is_ok());
+
+        let result = check("fn main() { let a = if true { 1 } else { 2.0 }; }");
+        assert!(matches!(result, Err(Error::Mismatch { .. })));
+
+        let result = check("fn main() { if 1 { } }");
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Bool,
+                found: Type::Int64
+            })
+        ));
+    }
+
+    #[test]
+    fn if_without_else_is_unit() {
+        let result = check("fn main() { if true { 1 } }");
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Unit,
+                found: Type::Int64
+            })
+        ));
+    }
+
+    #[test]
+    fn break_requires_enclosing_loop() {
+        assert!(
+            check("fn main() { 'outer: while true { while false { break 'outer; } continue; } }")
+                .is_ok()
+        );
+
+        let result = check("fn main() { break; }");
+        assert!(matches!(result, Err(Error::OutsideLoop("break"))));
+
+        let result = check("fn main() { while true { continue 'missing; } }");
+        assert!(matches!(result, Err(Error::UndefinedLabel(label)) if label == "missing"));
+    }
+
+    #[test]
+    fn calls_match_signatures() {
+        let source = "fn add(a: i64, b: i64) -> i64 { a + b } fn main() -> i64 { add(1, 2) }";
+        assert!(check(source).is_ok());
+
+        let result = check("fn add(a: i64, b: i64) -> i64 { a + b } fn main() { add(1); }");
+        assert!(matches!(
+            result,
+            Err(Error::ArityMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+
+        let result = check("fn f() -> i64 { return 1.0; } fn main() {}");
+        assert!(matches!(result, Err(Error::Mismatch { .. })));
+
+        assert!(matches!(check("fn f() {}"), Err(Error::MissingMain)));
+    }
+
+    #[test]
+    fn constants_must_be_foldable() {
+        let result = check("fn f() -> i64 { 1 } const A: i64 = f(); fn main() {}");
+        assert!(matches!(result, Err(Error::NotConstant)));
+
+        let result = check("const A: i64 = B; const B: i64 = A + 1; fn main() {}");
+        assert!(matches!(result, Err(Error::CyclicConstant(_))));
+
+        let result = check("const A: i64 = 1 / (2 - 2); fn main() {}");
+        assert!(matches!(
+            result,
+            Err(Error::ConstEvaluation {
+                reason: "division by zero",
+                ..
+            })
+        ));
+
+        let result = check("const A: u64 = 1; fn main() {}");
+        assert!(matches!(result, Err(Error::Mismatch { .. })));
+    }
+
+    #[test]
+    fn constants_are_not_assignable() {
+        let result = check("const A: i64 = 1; fn main() { A = 2; }");
+        assert!(matches!(result, Err(Error::AssignToConstant(name)) if name == "A"));
+    }
+
+    #[test]
+    fn let_binding_is_scoped_to_block() {
+        let result = check("fn main() { { let a = 1; } a; }");
+        assert!(matches!(result, Err(Error::UndefinedVariable(name)) if name == "a"));
+    }
+
+    #[test]
+    fn annotations_are_resolved() {
+        assert!(
+            check("fn main() { let a: i64 = 1; let b: ((bool)) = true; let c: () = (); }").is_ok()
+        );
+
+        let result = check("fn main() { let a: u64 = 1; }");
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Uint64,
+                found: Type::Int64
+            })
+        ));
+
+        let result = check("fn main() { let a: string = 1; }");
+        assert!(matches!(result, Err(Error::UndefinedType(name)) if name == "string"));
+    }
+
+    #[test]
+    fn tuple_patterns_match_shape() {
+        let module = check("fn main() -> i64 { let (a, (b, c)) = (1, (true, 2.0)); a }");
+        assert!(module.is_ok());
+
+        let result = check("fn main() { let (a, b) = (1, 2, 3); }");
+        assert!(matches!(
+            result,
+            Err(Error::PatternMismatch { arity: 2, .. })
+        ));
+
+        let result = check("fn main() { let (a, a) = (1, 2); }");
+        assert!(matches!(result, Err(Error::DuplicateBinding(name)) if name == "a"));
+
+        let result = check("fn main() { let t = (1, 2); t.2; }");
+        assert!(matches!(result, Err(Error::UnknownField { field, .. }) if field == "2"));
+    }
+
+    #[test]
+    fn diverging_expressions_coerce_to_any_type() {
+        let source = "
+            fn abs(a: i64) -> i64 {
+                let b: i64 = if a < 0 { return -a; } else { a };
+                b
+            }
+            fn fail() -> ! { panic() }
+            fn main() -> i64 {
+                while true { let x: (i64, f64) = if false { break; } else { continue; }; }
+                if true { fail() } else { abs(1) }
+            }
+        ";
+        assert!(check(source).is_ok());
+
+        let module = check("fn f() -> i64 { return 1; 2 } fn main() {}").expect("should typecheck");
+        let messages = module
+            .warnings
+            .iter()
+            .map(|w| w.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["unreachable expression"]);
+
+        let module = check("fn main() { panic(); let a = 1; a; }").expect("should typecheck");
+        let messages = module
+            .warnings
+            .iter()
+            .map(|w| w.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["unreachable statement"]);
+
+        let result = check("fn f() -> ! { 1; } fn main() {}");
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Never,
+                found: Type::Unit
+            })
+        ));
+    }
+
+    #[test]
+    fn strings_only_add_to_strings() {
+        assert!(check("fn main() -> i64 { let s: str = \"a\" + \"b\"; len(s) }").is_ok());
+
+        let result = check("fn main() { \"a\" + 1; }");
+        assert!(matches!(result, Err(Error::InvalidInfix(_))));
+
+        let result = check("fn main() { \"a\" * \"b\"; }");
+        assert!(matches!(result, Err(Error::InvalidInfix(_))));
+    }
+
+    #[test]
+    fn arrays_hold_one_element_type() {
+        assert!(check("fn main() { let a: [[f64]] = [[], [1.0]]; a[0][0] = 2.0; }").is_ok());
+
+        let result = check("fn main() { let a = [1, true]; }");
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Int64,
+                found: Type::Bool
+            })
+        ));
+
+        let result = check("fn main() { let a = []; push(a, 1); }");
+        assert!(matches!(result, Err(Error::AnnotationNeeded(_))));
+
+        // one array must not be usable as both `[i64]` and `[str]`
+        let source = "fn main() {
+            let a = [];
+            let b: [i64] = a;
+            let c: [str] = a;
+            push(b, 7);
+            println(c[0]);
+        }";
+        assert!(matches!(check(source), Err(Error::AnnotationNeeded(_))));
+        assert!(check("fn main() { let a: [i64] = []; push(a, 7); }").is_ok());
+
+        let result = check("fn main() { let a = 1; a[0]; }");
+        assert!(matches!(result, Err(Error::NotIndexable(Type::Int64))));
+
+        let result = check("fn main() { pop(1); }");
+        assert!(matches!(result, Err(Error::InvalidBuiltinCall("pop"))));
+
+        let result = check("fn len(a: i64) -> i64 { a } fn main() {}");
+        assert!(matches!(result, Err(Error::DuplicateDefinition(name)) if name == "len"));
+    }
+
+    #[test]
+    fn struct_fields_are_checked() {
+        let point = "struct Point { x: f64, y: f64 }";
+
+        let result = check(&format!(
+            "{point} fn main() {{ let p = Point {{ x: 1.0, y: 2.0 }}; p.z; }}"
+        ));
+        let Err(Error::UnknownStructField {
+            name,
+            field,
+            available,
+        }) = result
+        else {
+            panic!("expected unknown field error");
+        };
+        assert_eq!((name.as_str(), field.as_str()), ("Point", "z"));
+        assert_eq!(available, ["`x`", "`y`"]);
+
+        let result = check(&format!("{point} fn main() {{ Point {{ x: 1.0 }}; }}"));
+        assert!(matches!(result, Err(Error::MissingFields { fields, .. }) if fields == ["`y`"]));
+
+        let result = check(&format!(
+            "{point} fn main() {{ Point {{ x: 1.0, x: 2.0, y: 0.0 }}; }}"
+        ));
+        assert!(matches!(result, Err(Error::DuplicateField(field)) if field == "x"));
+
+        let result = check(&format!(
+            "{point} fn main() {{ let p = Point {{ x: 1.0, y: 2.0 }}; p.x = 1; }}"
+        ));
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Float64,
+                found: Type::Int64
+            })
+        ));
+
+        let result = check("fn main() { Line { a: 1 }; }");
+        assert!(matches!(result, Err(Error::UndefinedType(name)) if name == "Line"));
+    }
+
+    #[test]
+    fn matches_are_exhaustive() {
+        let shape = "enum Shape { Circle(f64), Rect(f64, f64), Empty }";
+
+        let source = format!(
+            "{shape} fn area(s: Shape) -> f64 {{
+                match s {{ Shape::Circle(r) => r * r, Shape::Rect(w, h) => w * h, Shape::Empty => 0.0 }}
+            }} fn main() {{}}"
+        );
+        assert!(check(&source).is_ok());
+
+        let result = check(&format!(
+            "{shape} fn main() {{ match Shape::Empty {{ Shape::Circle(_) => (), Shape::Empty => () }} }}"
+        ));
+        assert!(
+            matches!(result, Err(Error::NonExhaustive(missing)) if missing == "Shape::Rect(_, _)")
+        );
+
+        let result = check("fn main() { match (true, 1) { (true, _) => (), (_, 0) => () } }");
+        assert!(matches!(result, Err(Error::NonExhaustive(missing)) if missing == "(false, _)"));
+
+        let result = check("fn main() { match 1 { 0 => (), _ => (), 1 => () } }");
+        assert!(matches!(result, Err(Error::UnreachableArm(2))));
+
+        let result = check(&format!(
+            "{shape} fn main() {{ let Shape::Circle(r) = Shape::Circle(1.0); }}"
+        ));
+        assert!(
+            matches!(result, Err(Error::RefutablePattern(missing)) if missing == "Shape::Rect(_, _)")
+        );
+    }
+
+    #[test]
+    fn variants_are_checked() {
+        let shape = "enum Shape { Circle(f64), Empty }";
+
+        let result = check(&format!("{shape} fn main() {{ Shape::Circle(1.0, 2.0); }}"));
+        assert!(matches!(
+            result,
+            Err(Error::VariantArity {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+
+        let result = check(&format!("{shape} fn main() {{ Shape::Square; }}"));
+        assert!(
+            matches!(result, Err(Error::UnknownVariant { variant, .. }) if variant == "Square")
+        );
+
+        let result = check(&format!(
+            "{shape} fn main() {{ match Shape::Empty {{ Shape::Circle(1) => (), _ => () }} }}"
+        ));
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Float64,
+                found: Type::Int64
+            })
+        ));
+    }
+
+    #[test]
+    fn closures_have_function_types() {
+        let source = "
+            fn apply(f: fn(i64) -> i64, x: i64) -> i64 { f(x) }
+            fn main() -> i64 {
+                let k = 2;
+                let double = |x: i64| x * k;
+                let pick = |b: bool| -> i64 { if b { return 1; } 0 };
+                apply(double, 1) + pick(true) + apply(|x: i64| x, 3)
+            }
+        ";
+        let module = check(source).expect("should typecheck");
+        let captures = module.captures.values().collect::<Vec<_>>();
+        assert!(captures.contains(&&vec!["k".to_owned()]));
+
+        let result = check("fn main() { let k = 1; let f = || { k = 2; }; }");
+        assert!(matches!(result, Err(Error::AssignToCapture(name)) if name == "k"));
+
+        let result = check("fn main() { let k = 1; k(); }");
+        assert!(matches!(result, Err(Error::NotCallable(Type::Int64))));
+
+        let result = check("fn main() { let f = |x: i64| x; f(true); }");
+        assert!(matches!(
+            result,
+            Err(Error::Mismatch {
+                expected: Type::Int64,
+                found: Type::Bool
+            })
+        ));
+
+        let result = check("fn main() { while true { let f = || { break; }; } }");
+        assert!(matches!(result, Err(Error::OutsideLoop("break"))));
+    }
+
+    #[test]
+    fn natives_have_signatures() {
+        let mut env = TypeEnv::new();
+        env.register_native("clock", vec![], Type::Float64, |_, _| {
+            Ok(Value::Float64(0.0))
+        })
+        .expect("clock is not registered yet");
+        let result = env.register_native("clock", vec![], Type::Unit, |_, _| Ok(Value::Unit));
+        assert_eq!(result, Err(RegisterError::Duplicate("clock".to_owned())));
+        let result = env.register_native("len", vec![], Type::Unit, |_, _| Ok(Value::Unit));
+        assert_eq!(result, Err(RegisterError::Builtin("len".to_owned())));
+        let check = |source: &str| {
+            let syntax = Parser::new(Lexer::new(source))
+                .parse()
+                .expect("should parse");
+            Typer::new(&env).check(syntax).map_err(|error| error.error)
+        };
+
+        assert!(check("fn main() { let t: f64 = clock(); let f: fn() -> f64 = clock; }").is_ok());
+
+        let result = check("fn main() { clock(1); }");
+        assert!(matches!(
+            result,
+            Err(Error::ArityMismatch {
+                expected: 0,
+                found: 1
+            })
+        ));
+
+        let result = check("fn main() -> i64 { clock() }");
+        assert!(matches!(result, Err(Error::Mismatch { .. })));
+
+        let result = check("fn clock() {} fn main() {}");
+        assert!(matches!(result, Err(Error::DuplicateDefinition(name)) if name == "clock"));
+    }
+
+    #[test]
+    fn casts_are_numeric() {
+        let module = check("const C: u64 = -1 as u64; fn main() -> i64 { 1 + 2u64 as i64 }")
+            .expect("should typecheck");
+        assert!(matches!(module.consts["C"], Value::Uint64(u64::MAX)));
+
+        let result = check("fn main() { true as i64; }");
+        assert!(matches!(
+            result,
+            Err(Error::InvalidCast {
+                from: Type::Bool,
+                to: Type::Int64
+            })
+        ));
+
+        let result = check("fn main() { 1u64 + 1; }");
+        assert!(matches!(result, Err(Error::InvalidInfix(_))));
+    }
+
+    #[test]
+    fn operator_errors_explain_the_operands() {
+        let source = "fn half() -> f64 { let x = 1.5; x + 1 } fn main() {}";
+        let infix = match check(source) {
+            Err(Error::InvalidInfix(infix)) => infix,
+            result => panic!("`f64 + i64` is not defined, found {result:?}"),
+        };
+        assert_eq!((infix.op, &infix.lhs), ("+", &Type::Float64));
+        assert_eq!(infix.rhs, Type::Int64);
+        let text = |span: Span| &source[span.start..span.end];
+        assert_eq!((text(infix.lhs_span), text(infix.rhs_span)), ("x", "1"));
+        assert!(infix.accepted.contains(&(Type::Str, Type::Str)));
+        assert!(!infix.accepted.contains(&(Type::Float64, Type::Int64)));
+        assert_eq!(infix.fix.as_deref(), Some("use a float literal: `1.0`"));
+
+        let fix = |source| match check(source) {
+            Err(Error::InvalidInfix(infix)) => infix.fix,
+            Err(Error::InvalidPrefix(prefix)) => prefix.fix,
+            result => panic!("expected an operator error, found {result:?}"),
+        };
+        assert_eq!(
+            fix("fn main() { let n = 2u64; n * 3; }").as_deref(),
+            Some("use a `u64` literal: `3u64`")
+        );
+        assert_eq!(
+            fix("fn f(a: i64, b: u64) -> i64 { a - b } fn main() {}").as_deref(),
+            Some("convert the right operand with `as i64`")
+        );
+        assert_eq!(
+            fix("fn f(a: u64) -> u64 { -a } fn main() {}").as_deref(),
+            Some("convert the operand with `as f64`")
+        );
+        assert_eq!(
+            fix("fn f(a: i64) -> i64 { !a } fn main() {}").as_deref(),
+            Some("use `~` for the bitwise complement of an integer")
+        );
+        assert_eq!(fix("fn main() { \"a\" + 1; }"), None);
+    }
+
+    #[test]
+    fn bitwise_operators_take_integers() {
+        let module =
+            check("const MASK: u64 = ~(1u64 << 63u64); fn main() {}").expect("should typecheck");
+        assert!(matches!(module.consts["MASK"], Value::Uint64(u) if u == u64::MAX >> 1));
+
+        let result = check("const BAD: i64 = 1 << 64; fn main() {}");
+        assert!(matches!(result, Err(Error::ConstEvaluation { .. })));
+
+        assert!(matches!(
+            check("fn main() { 1.0 & 2.0; }"),
+            Err(Error::InvalidInfix(_))
+        ));
+        assert!(matches!(
+            check("fn main() { 1 << 2u64; }"),
+            Err(Error::InvalidInfix(_))
+        ));
+        assert!(matches!(
+            check("fn main() { ~true; }"),
+            Err(Error::InvalidPrefix(_))
+        ));
+    }
+
+    #[test]
+    fn wrapping_and_saturating_constants_fold() {
+        let source = "
+            const WRAPPED: u64 = 0u64 -% 1u64;
+            const CLAMPED: i64 = 9223372036854775807 +| 1;
+            fn main() {}
+        ";
+        let module = check(source).expect("should typecheck");
+        assert!(matches!(module.consts["WRAPPED"], Value::Uint64(u64::MAX)));
+        assert!(matches!(module.consts["CLAMPED"], Value::Int64(i64::MAX)));
+
+        let result = check("const C: i64 = 9223372036854775807 + 1; fn main() {}");
+        assert!(matches!(result, Err(Error::ConstEvaluation { .. })));
+
+        assert!(matches!(
+            check("fn main() { 1.0 +% 2.0; }"),
+            Err(Error::InvalidInfix(_))
+        ));
     }
 
-    fn resolve_prefix(&self, op: operator::Prefix, exp: Type) -> Result<Type> {
-        self.prefix.get(&(op, exp)).copied().ok_or(Error {})
+    #[test]
+    fn errors_point_at_the_innermost_node() {
+        let source = "fn main() -> i64 {\n    let a = 1;\n    a + true\n}";
+        let syntax = Parser::new(Lexer::new(source))
+            .parse()
+            .expect("should parse");
+        let env = TypeEnv::new();
+        let error = Typer::new(&env)
+            .check(syntax)
+            .expect_err("bool is not an i64");
+        let span = error.span.expect("error has a location");
+        assert_eq!(&source[span.start..span.end], "a + true");
+        assert_eq!(error.line, 3);
     }
 }
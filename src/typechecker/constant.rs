@@ -0,0 +1,111 @@
+use crate::grammar::operator::{Infix, Prefix};
+use crate::runtime::Value;
+use crate::semantic::Type;
+use crate::vm::shift_amount;
+
+type Fold = std::result::Result<Value, &'static str>;
+
+const OVERFLOW: &str = "arithmetic overflow";
+const DIVISION_BY_ZERO: &str = "division by zero";
+const SHIFT_OVERFLOW: &str = "shift amount out of range";
+
+pub(super) fn fold_infix(op: Infix, lhs: Value, rhs: Value) -> Fold {
+    use Value::*;
+
+    let value = match (lhs, rhs) {
+        (Int64(l), Int64(r)) => match op {
+            Infix::Div | Infix::Rem if r == 0 => return Err(DIVISION_BY_ZERO),
+            Infix::Plus => Int64(l.checked_add(r).ok_or(OVERFLOW)?),
+            Infix::Minus => Int64(l.checked_sub(r).ok_or(OVERFLOW)?),
+            Infix::Mul => Int64(l.checked_mul(r).ok_or(OVERFLOW)?),
+            Infix::Div => Int64(l.checked_div(r).ok_or(OVERFLOW)?),
+            Infix::Rem => Int64(l.wrapping_rem(r)),
+            Infix::WrappingPlus => Int64(l.wrapping_add(r)),
+            Infix::WrappingMinus => Int64(l.wrapping_sub(r)),
+            Infix::WrappingMul => Int64(l.wrapping_mul(r)),
+            Infix::SaturatingPlus => Int64(l.saturating_add(r)),
+            Infix::SaturatingMinus => Int64(l.saturating_sub(r)),
+            Infix::SaturatingMul => Int64(l.saturating_mul(r)),
+            Infix::BitAnd => Int64(l & r),
+            Infix::BitOr => Int64(l | r),
+            Infix::BitXor => Int64(l ^ r),
+            Infix::Shl => Int64(l << shift_amount(r).map_err(|_| SHIFT_OVERFLOW)?),
+            Infix::Shr => Int64(l >> shift_amount(r).map_err(|_| SHIFT_OVERFLOW)?),
+            _ => Bool(compare(op, l, r)),
+        },
+        (Uint64(l), Uint64(r)) => match op {
+            Infix::Div | Infix::Rem if r == 0 => return Err(DIVISION_BY_ZERO),
+            Infix::Plus => Uint64(l.checked_add(r).ok_or(OVERFLOW)?),
+            Infix::Minus => Uint64(l.checked_sub(r).ok_or(OVERFLOW)?),
+            Infix::Mul => Uint64(l.checked_mul(r).ok_or(OVERFLOW)?),
+            Infix::Div => Uint64(l / r),
+            Infix::Rem => Uint64(l % r),
+            Infix::WrappingPlus => Uint64(l.wrapping_add(r)),
+            Infix::WrappingMinus => Uint64(l.wrapping_sub(r)),
+            Infix::WrappingMul => Uint64(l.wrapping_mul(r)),
+            Infix::SaturatingPlus => Uint64(l.saturating_add(r)),
+            Infix::SaturatingMinus => Uint64(l.saturating_sub(r)),
+            Infix::SaturatingMul => Uint64(l.saturating_mul(r)),
+            Infix::BitAnd => Uint64(l & r),
+            Infix::BitOr => Uint64(l | r),
+            Infix::BitXor => Uint64(l ^ r),
+            Infix::Shl => Uint64(l << shift_amount(r).map_err(|_| SHIFT_OVERFLOW)?),
+            Infix::Shr => Uint64(l >> shift_amount(r).map_err(|_| SHIFT_OVERFLOW)?),
+            _ => Bool(compare(op, l, r)),
+        },
+        (Float64(l), Float64(r)) => match op {
+            Infix::Plus => Float64(l + r),
+            Infix::Minus => Float64(l - r),
+            Infix::Mul => Float64(l * r),
+            Infix::Div => Float64(l / r),
+            Infix::Rem => Float64(l % r),
+            _ => Bool(compare(op, l, r)),
+        },
+        (Bool(l), Bool(r)) => match op {
+            Infix::And => Bool(l && r),
+            Infix::Or => Bool(l || r),
+            _ => Bool(compare(op, l, r)),
+        },
+        _ => unreachable!("constant operands are typechecked"),
+    };
+
+    Ok(value)
+}
+
+pub(super) fn fold_prefix(op: Prefix, value: Value) -> Fold {
+    let value = match (op, value) {
+        (Prefix::Minus, Value::Int64(i)) => Value::Int64(i.checked_neg().ok_or(OVERFLOW)?),
+        (Prefix::Minus, Value::Float64(f)) => Value::Float64(-f),
+        (Prefix::Negate, Value::Bool(b)) => Value::Bool(!b),
+        (Prefix::BitNot, Value::Int64(i)) => Value::Int64(!i),
+        (Prefix::BitNot, Value::Uint64(u)) => Value::Uint64(!u),
+        _ => unreachable!("constant operands are typechecked"),
+    };
+
+    Ok(value)
+}
+
+/// Same semantics as the conversion opcodes of the VM
+pub(super) fn fold_cast(value: Value, to: &Type) -> Value {
+    match (value, to) {
+        (Value::Int64(i), Type::Uint64) => Value::Uint64(i as u64),
+        (Value::Int64(i), Type::Float64) => Value::Float64(i as f64),
+        (Value::Uint64(u), Type::Int64) => Value::Int64(u as i64),
+        (Value::Uint64(u), Type::Float64) => Value::Float64(u as f64),
+        (Value::Float64(f), Type::Int64) => Value::Int64(f as i64),
+        (Value::Float64(f), Type::Uint64) => Value::Uint64(f as u64),
+        (value, _) => value,
+    }
+}
+
+fn compare<T: PartialOrd>(op: Infix, l: T, r: T) -> bool {
+    match op {
+        Infix::Equal => l == r,
+        Infix::NotEqual => l != r,
+        Infix::Less => l < r,
+        Infix::LessEqual => l <= r,
+        Infix::Greater => l > r,
+        Infix::GreaterEqual => l >= r,
+        _ => unreachable!("{op:?} is not a comparison"),
+    }
+}
@@ -1,7 +1,218 @@
 use thiserror::Error;
 
+use crate::diagnostic::Diagnostic;
+use crate::grammar::Span;
+use crate::semantic::Type;
+
 #[derive(Debug, Error)]
-#[error("Type Error during ast construction")]
-pub struct Error {}
+pub enum Error {
+    #[error("cannot apply `{}` to `{}` and `{}`", .0.op, .0.lhs, .0.rhs)]
+    InvalidInfix(Box<InfixMismatch>),
+    #[error("cannot apply unary `{}` to `{}`", .0.op, .0.operand)]
+    InvalidPrefix(Box<PrefixMismatch>),
+    #[error("cannot find value `{0}` in this scope")]
+    UndefinedVariable(String),
+    #[error("mismatched types: expected `{expected}`, found `{found}`")]
+    Mismatch { expected: Type, found: Type },
+    #[error("mismatched types: expected a tuple with {arity} elements, found `{found}`")]
+    PatternMismatch { arity: usize, found: Type },
+    #[error("identifier `{0}` is bound more than once in the same pattern")]
+    DuplicateBinding(String),
+    #[error("no field `{field}` on type `{ty}`")]
+    UnknownField { ty: Type, field: String },
+    #[error("no field `{field}` on struct `{name}`, available fields are: {}", .available.join(", "))]
+    UnknownStructField {
+        name: String,
+        field: String,
+        available: Vec<String>,
+    },
+    #[error("no variant `{variant}` in enum `{name}`")]
+    UnknownVariant { name: String, variant: String },
+    #[error("variant `{variant}` has {expected} fields, but {found} were supplied")]
+    VariantArity {
+        variant: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("non-exhaustive patterns: `{0}` not covered")]
+    NonExhaustive(String),
+    #[error("unreachable pattern in match arm {}", .0 + 1)]
+    UnreachableArm(usize),
+    #[error("refutable pattern in `let`: `{0}` not covered")]
+    RefutablePattern(String),
+    #[error("field `{0}` is specified more than once")]
+    DuplicateField(String),
+    #[error("missing fields {} in initializer of `{name}`", .fields.join(", "))]
+    MissingFields { name: String, fields: Vec<String> },
+    #[error("invalid left-hand side of assignment")]
+    InvalidAssignmentTarget,
+    #[error("cannot find function `{0}` in this scope")]
+    UndefinedFunction(String),
+    #[error("cannot find type `{0}` in this scope")]
+    UndefinedType(String),
+    #[error("the name `{0}` is defined multiple times")]
+    DuplicateDefinition(String),
+    #[error("invalid arguments to built-in function `{0}`")]
+    InvalidBuiltinCall(&'static str),
+    #[error("casting `{from}` as `{to}` is invalid")]
+    InvalidCast { from: Type, to: Type },
+    #[error("type annotations needed for `{0}`")]
+    AnnotationNeeded(Type),
+    #[error("cannot index into a value of type `{0}`")]
+    NotIndexable(Type),
+    #[error("expected function, found `{0}`")]
+    NotCallable(Type),
+    #[error("function takes {expected} arguments but {found} were supplied")]
+    ArityMismatch { expected: usize, found: usize },
+    #[error("cannot assign to `{0}`, closures capture variables by value")]
+    AssignToCapture(String),
+    #[error("constant `{0}` cannot be assigned to")]
+    AssignToConstant(String),
+    #[error("expression cannot be evaluated at compile time")]
+    NotConstant,
+    #[error("cycle detected when evaluating constant `{0}`")]
+    CyclicConstant(String),
+    #[error("evaluation of constant `{name}` failed: {reason}")]
+    ConstEvaluation { name: String, reason: &'static str },
+    #[error("`main` function not found")]
+    MissingMain,
+    #[error("`main` must take no arguments and return `i64` or `()`")]
+    InvalidMain,
+    #[error("`{0}` outside of a loop")]
+    OutsideLoop(&'static str),
+    #[error("use of undeclared label `'{0}`")]
+    UndefinedLabel(String),
+}
+
+/// Operands of an infix operator that it is not defined for
+#[derive(Debug)]
+pub struct InfixMismatch {
+    pub op: &'static str,
+    pub lhs: Type,
+    pub rhs: Type,
+    pub lhs_span: Span,
+    pub rhs_span: Span,
+    /// Operand types the operator is defined for
+    pub accepted: Vec<(Type, Type)>,
+    pub fix: Option<String>,
+}
+
+/// Operand of a prefix operator that it is not defined for
+#[derive(Debug)]
+pub struct PrefixMismatch {
+    pub op: &'static str,
+    pub operand: Type,
+    pub operand_span: Span,
+    pub accepted: Vec<Type>,
+    pub fix: Option<String>,
+}
+
+impl Error {
+    /// Short description of the offending node, shown under it
+    fn label(&self) -> String {
+        match self {
+            Error::InvalidInfix(infix) => {
+                format!(
+                    "no implementation for `{} {} {}`",
+                    infix.lhs, infix.op, infix.rhs
+                )
+            }
+            Error::InvalidPrefix(prefix) => {
+                format!("no implementation for `{}{}`", prefix.op, prefix.operand)
+            }
+            Error::Mismatch { expected, .. } => format!("expected `{expected}`"),
+            Error::UndefinedVariable(_) | Error::UndefinedFunction(_) => {
+                "not found in this scope".to_owned()
+            }
+            Error::UndefinedType(_) => "not a known type".to_owned(),
+            Error::NotCallable(_) => "call expression requires a function".to_owned(),
+            Error::NonExhaustive(pattern) => format!("pattern `{pattern}` not covered"),
+            Error::AnnotationNeeded(_) => "cannot infer the element type".to_owned(),
+            _ => String::new(),
+        }
+    }
+
+    /// Other nodes involved, with what they have to do with the error
+    fn secondary(&self) -> Vec<(Span, String)> {
+        match self {
+            Error::InvalidInfix(infix) => vec![
+                (infix.lhs_span, format!("`{}`", infix.lhs)),
+                (infix.rhs_span, format!("`{}`", infix.rhs)),
+            ],
+            Error::InvalidPrefix(prefix) => {
+                vec![(prefix.operand_span, format!("`{}`", prefix.operand))]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn note(&self) -> Option<String> {
+        let (op, accepted) = match self {
+            Error::InvalidInfix(infix) => {
+                let op = infix.op;
+                let accepted = infix
+                    .accepted
+                    .iter()
+                    .map(|(lhs, rhs)| format!("`{lhs} {op} {rhs}`"))
+                    .collect::<Vec<_>>();
+                (op, accepted)
+            }
+            Error::InvalidPrefix(prefix) => {
+                let op = prefix.op;
+                let accepted = prefix
+                    .accepted
+                    .iter()
+                    .map(|operand| format!("`{op}{operand}`"))
+                    .collect::<Vec<_>>();
+                (op, accepted)
+            }
+            _ => return None,
+        };
+        Some(format!("`{op}` is defined for {}", accepted.join(", ")))
+    }
+
+    fn help(&self) -> Option<String> {
+        let help = match self {
+            Error::InvalidInfix(infix) => return infix.fix.clone(),
+            Error::InvalidPrefix(prefix) => return prefix.fix.clone(),
+            Error::InvalidCast { .. } => "only `i64`, `u64` and `f64` can be cast with `as`",
+            Error::NonExhaustive(_) => "add a match arm for it, or a wildcard pattern `_`",
+            Error::RefutablePattern(_) => "use `match` to handle the values the pattern rejects",
+            Error::AssignToCapture(_) => "return the new value from the closure instead",
+            Error::AnnotationNeeded(_) => "give the binding a type, like `let a: [i64] = []`",
+            Error::MissingMain => "add `fn main() -> i64 { .. }` to the program",
+            _ => return None,
+        };
+        Some(help.to_owned())
+    }
+}
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// Type error with the innermost node it was found in, if any
+#[derive(Debug, Error)]
+#[error("{error} at line {line}")]
+pub struct TypeError {
+    pub error: Error,
+    pub span: Option<Span>,
+    pub line: usize,
+}
+
+impl From<TypeError> for Diagnostic {
+    fn from(error: TypeError) -> Self {
+        let mut diagnostic = Diagnostic::error(error.error.to_string());
+        if let Some(span) = error.span {
+            diagnostic = diagnostic.with_primary(span, error.error.label());
+        }
+        for (span, label) in error.error.secondary() {
+            diagnostic = diagnostic.with_secondary(span, label);
+        }
+        if let Some(note) = error.error.note() {
+            diagnostic = diagnostic.with_note(note);
+        }
+        if let Some(help) = error.error.help() {
+            diagnostic = diagnostic.with_help(help);
+        }
+        diagnostic
+    }
+}
@@ -0,0 +1,267 @@
+//! Exhaustiveness and reachability of patterns.
+//!
+//! Patterns are lowered to constructors applied to sub-patterns and checked
+//! with the usefulness algorithm from Maranget's "Warnings for pattern
+//! matching": an arm is unreachable when it is not useful after the arms
+//! above it, and a match is exhaustive when a wildcard is not useful after
+//! all of its arms.
+
+use std::collections::HashMap;
+use std::{iter, slice};
+
+use crate::grammar::*;
+use crate::semantic::Type;
+
+/// Variant names and payload types of an enum in declaration order
+pub(super) type Variants = Vec<(String, Vec<Type>)>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Constructor {
+    /// enum variant by its tag
+    Variant(usize),
+    Bool(bool),
+    /// the only constructor of a tuple or `()`
+    Tuple,
+    /// number or string literal by its text, these never cover their type
+    Literal(String),
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Wildcard,
+    Constructor(Constructor, Vec<Pattern>),
+}
+
+/// Typechecked patterns matched against one value, in order
+pub(super) struct Arms<'a> {
+    enums: &'a HashMap<String, Variants>,
+    ty: Type,
+    patterns: Vec<Pattern>,
+}
+
+impl<'a> Arms<'a> {
+    pub(super) fn new(
+        enums: &'a HashMap<String, Variants>,
+        arena: &Arena,
+        patterns: &[pattern::Id],
+        ty: Type,
+    ) -> Self {
+        let mut arms = Self {
+            enums,
+            ty,
+            patterns: Vec::new(),
+        };
+        arms.patterns = patterns.iter().map(|id| arms.lower(arena, *id)).collect();
+        arms
+    }
+
+    /// Position of the first arm matching only values covered by the arms above it
+    pub(super) fn unreachable(&self) -> Option<usize> {
+        let types = slice::from_ref(&self.ty);
+        (0..self.patterns.len()).find(|&arm| {
+            let above = self.rows(&self.patterns[..arm]);
+            !self.useful(&above, &self.patterns[arm..=arm], types)
+        })
+    }
+
+    /// A value none of the arms match, written as a pattern
+    pub(super) fn missing(&self) -> Option<String> {
+        let rows = self.rows(&self.patterns);
+        let witness = self.witness(&rows, slice::from_ref(&self.ty))?;
+        Some(self.render(&witness[0], &self.ty))
+    }
+
+    fn rows(&self, patterns: &[Pattern]) -> Vec<Vec<Pattern>> {
+        patterns
+            .iter()
+            .map(|pattern| vec![pattern.clone()])
+            .collect()
+    }
+
+    fn lower(&self, arena: &Arena, id: pattern::Id) -> Pattern {
+        match &arena[id].kind {
+            pattern::Kind::Identifier(_) | pattern::Kind::Wildcard | pattern::Kind::Error => {
+                Pattern::Wildcard
+            }
+            pattern::Kind::Literal(literal) => {
+                let constructor = match &arena[*literal].kind {
+                    expression::Kind::Bool(b) => Constructor::Bool(*b),
+                    expression::Kind::Int64(i) => Constructor::Literal(i.to_string()),
+                    expression::Kind::Uint64(u) => Constructor::Literal(u.to_string()),
+                    expression::Kind::Float64(f) => Constructor::Literal(format!("{f:?}")),
+                    expression::Kind::Str(s) => Constructor::Literal(format!("{s:?}")),
+                    kind => unreachable!("not a literal pattern: {kind:?}"),
+                };
+                Pattern::Constructor(constructor, Vec::new())
+            }
+            pattern::Kind::Tuple(elements) => {
+                let elements = elements.iter().map(|el| self.lower(arena, *el)).collect();
+                Pattern::Constructor(Constructor::Tuple, elements)
+            }
+            pattern::Kind::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                let tag = self.enums[enum_name]
+                    .iter()
+                    .position(|(name, _)| name == variant)
+                    .expect("variant is typechecked");
+                let fields = fields
+                    .iter()
+                    .map(|field| self.lower(arena, *field))
+                    .collect();
+                Pattern::Constructor(Constructor::Variant(tag), fields)
+            }
+        }
+    }
+
+    /// All constructors of a type, `None` when it has too many to list
+    fn signature(&self, ty: &Type) -> Option<Vec<Constructor>> {
+        let constructors = match ty {
+            Type::Bool => vec![Constructor::Bool(false), Constructor::Bool(true)],
+            Type::Unit | Type::Tuple(_) => vec![Constructor::Tuple],
+            Type::Enum(name) => (0..self.enums[name].len())
+                .map(Constructor::Variant)
+                .collect(),
+            Type::Never => Vec::new(),
+            _ => return None,
+        };
+        Some(constructors)
+    }
+
+    /// Types of the values a constructor holds
+    fn fields(&self, constructor: &Constructor, ty: &Type) -> Vec<Type> {
+        match (constructor, ty) {
+            (Constructor::Variant(tag), Type::Enum(name)) => self.enums[name][*tag].1.clone(),
+            (Constructor::Tuple, Type::Tuple(elements)) => elements.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether some value matched by `row` is matched by none of `rows`
+    fn useful(&self, rows: &[Vec<Pattern>], row: &[Pattern], types: &[Type]) -> bool {
+        let Some((head, rest)) = row.split_first() else {
+            return rows.is_empty();
+        };
+        let (ty, rest_types) = types.split_first().expect("a type per column");
+
+        match head {
+            Pattern::Constructor(constructor, args) => {
+                let row = [args.as_slice(), rest].concat();
+                let types = [self.fields(constructor, ty).as_slice(), rest_types].concat();
+                self.useful(&specialize(rows, constructor, args.len()), &row, &types)
+            }
+            Pattern::Wildcard => match self.complete_signature(rows, ty) {
+                Some(constructors) => constructors.iter().any(|constructor| {
+                    let fields = self.fields(constructor, ty);
+                    let row = [wildcards(fields.len()).as_slice(), rest].concat();
+                    let types = [fields.as_slice(), rest_types].concat();
+                    self.useful(&specialize(rows, constructor, fields.len()), &row, &types)
+                }),
+                None => self.useful(&default(rows), rest, rest_types),
+            },
+        }
+    }
+
+    /// Values, one per column, matched by none of `rows`
+    fn witness(&self, rows: &[Vec<Pattern>], types: &[Type]) -> Option<Vec<Pattern>> {
+        let Some((ty, rest_types)) = types.split_first() else {
+            return rows.is_empty().then(Vec::new);
+        };
+
+        if let Some(constructors) = self.complete_signature(rows, ty) {
+            return constructors.into_iter().find_map(|constructor| {
+                let fields = self.fields(&constructor, ty);
+                let arity = fields.len();
+                let types = [fields.as_slice(), rest_types].concat();
+                let mut witness = self.witness(&specialize(rows, &constructor, arity), &types)?;
+                let rest = witness.split_off(arity);
+                let head = Pattern::Constructor(constructor, witness);
+                Some(iter::once(head).chain(rest).collect())
+            });
+        }
+
+        let rest = self.witness(&default(rows), rest_types)?;
+        let heads = head_constructors(rows);
+        let missing = self
+            .signature(ty)
+            .and_then(|all| all.into_iter().find(|c| !heads.contains(&c)));
+        let head = match missing {
+            Some(constructor) => {
+                let arity = self.fields(&constructor, ty).len();
+                Pattern::Constructor(constructor, wildcards(arity))
+            }
+            None => Pattern::Wildcard,
+        };
+        Some(iter::once(head).chain(rest).collect())
+    }
+
+    /// Signature of the type when the first column uses all of its constructors
+    fn complete_signature(&self, rows: &[Vec<Pattern>], ty: &Type) -> Option<Vec<Constructor>> {
+        let heads = head_constructors(rows);
+        self.signature(ty)
+            .filter(|all| all.iter().all(|constructor| heads.contains(&constructor)))
+    }
+
+    fn render(&self, pattern: &Pattern, ty: &Type) -> String {
+        let Pattern::Constructor(constructor, args) = pattern else {
+            return "_".to_owned();
+        };
+        let fields = self.fields(constructor, ty);
+        let args = args
+            .iter()
+            .zip(&fields)
+            .map(|(arg, ty)| self.render(arg, ty))
+            .collect::<Vec<_>>();
+
+        match (constructor, ty) {
+            (Constructor::Variant(tag), Type::Enum(name)) => {
+                let variant = &self.enums[name][*tag].0;
+                match args.is_empty() {
+                    true => format!("{name}::{variant}"),
+                    false => format!("{name}::{variant}({})", args.join(", ")),
+                }
+            }
+            (Constructor::Tuple, _) if args.len() == 1 => format!("({},)", args[0]),
+            (Constructor::Tuple, _) => format!("({})", args.join(", ")),
+            (Constructor::Bool(b), _) => b.to_string(),
+            (Constructor::Literal(text), _) => text.clone(),
+            (Constructor::Variant(_), _) => unreachable!("variant of a non-enum type"),
+        }
+    }
+}
+
+fn wildcards(count: usize) -> Vec<Pattern> {
+    vec![Pattern::Wildcard; count]
+}
+
+fn head_constructors(rows: &[Vec<Pattern>]) -> Vec<&Constructor> {
+    rows.iter()
+        .filter_map(|row| match &row[0] {
+            Pattern::Constructor(constructor, _) => Some(constructor),
+            Pattern::Wildcard => None,
+        })
+        .collect()
+}
+
+/// Rows that match `constructor` in the first column, with its arguments spliced in
+fn specialize(rows: &[Vec<Pattern>], constructor: &Constructor, arity: usize) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter_map(|row| match &row[0] {
+            Pattern::Wildcard => Some([wildcards(arity).as_slice(), &row[1..]].concat()),
+            Pattern::Constructor(head, args) if head == constructor => {
+                Some([args.as_slice(), &row[1..]].concat())
+            }
+            Pattern::Constructor(..) => None,
+        })
+        .collect()
+}
+
+/// Rows with a wildcard in the first column, without it
+fn default(rows: &[Vec<Pattern>]) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter(|row| matches!(row[0], Pattern::Wildcard))
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
@@ -1,6 +1,10 @@
 mod checker;
+mod constant;
 mod error;
+mod exhaustive;
 
 pub use checker::Typer;
 pub use error::Error;
 pub use error::Result;
+pub use error::TypeError;
+pub use error::{InfixMismatch, PrefixMismatch};
@@ -39,7 +39,30 @@ impl<'p> CallFrame<'p> {
         Ok((hi << 8) | lo)
     }
 
+    /// Move ip forward by `offset` bytes, staying inside the chunk.
+    pub fn jump(&mut self, offset: u16) -> Result<()> {
+        let target = self.ip + offset as usize;
+        if target > self.function.chunk.len() {
+            return Err(Error::InvalidJumpTarget);
+        }
+        self.ip = target;
+        Ok(())
+    }
+
+    /// Move ip back by `offset` bytes.
+    pub fn loop_back(&mut self, offset: u16) -> Result<()> {
+        self.ip = self
+            .ip
+            .checked_sub(offset as usize)
+            .ok_or(Error::InvalidJumpTarget)?;
+        Ok(())
+    }
+
     pub fn get_const(&self, idx: u16) -> Value {
         self.function.chunk.get_const(idx as usize)
     }
+
+    pub fn get_string(&self, idx: u16) -> &'p str {
+        self.function.chunk.get_string(idx as usize)
+    }
 }
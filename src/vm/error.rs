@@ -1,17 +1,66 @@
 use thiserror::Error;
 
+use crate::diagnostic::Diagnostic;
+use crate::grammar::Span;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("stack underflow")]
     StackUnderflow,
+    #[error("stack overflow")]
+    StackOverflow,
     #[error("type mismatch: {0}")]
     TypeMismatch(&'static str),
     #[error("global not defined: {0}")]
     GlobalNotDefined(u16),
+    #[error("explicit panic")]
+    Panic,
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("attempt to {0} with overflow")]
+    IntegerOverflow(&'static str),
+    #[error("attempt to shift with overflow, the amount must be in 0..64")]
+    ShiftOverflow,
+    #[error("index out of bounds: the len is {len} but the index is {index}")]
+    IndexOutOfBounds { index: i64, len: usize },
+    #[error("pop from an empty array")]
+    PopEmpty,
+    #[error("invalid local slot: {0}")]
+    InvalidLocal(u16),
+    #[error("invalid field index: {0}")]
+    InvalidField(u16),
+    /// Failure reported by a host function
+    #[error("{0}")]
+    Native(String),
     #[error("invalid jump target")]
     InvalidJumpTarget,
     #[error("expected OpCode")]
     InvalidOpCode,
 }
 
+/// Error raised while running a program, with the instruction that raised it
+#[derive(Debug, Error)]
+#[error("{error} in `{function}` at line {line}")]
+pub struct RuntimeError {
+    pub error: Error,
+    /// Name of the function executing the failing instruction
+    pub function: String,
+    pub line: usize,
+    /// Source of the failing instruction, if the program was compiled from one
+    pub span: Option<Span>,
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(error: RuntimeError) -> Self {
+        let diagnostic = Diagnostic::error(error.error.to_string());
+        match error.span {
+            Some(span) => diagnostic.with_primary(span, format!("in `{}`", error.function)),
+            None => {
+                let location = format!("in `{}` at line {}", error.function, error.line);
+                diagnostic.with_note(location)
+            }
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -4,7 +4,9 @@ mod vm;
 
 pub use error::Error;
 pub use error::Result;
+pub use error::RuntimeError;
 
 pub use vm::Vm;
+pub(crate) use vm::shift_amount;
 
 use call_frame::CallFrame;
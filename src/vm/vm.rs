@@ -2,12 +2,24 @@ use super::*;
 use crate::bytecode::OpCode;
 use crate::runtime::*;
 
+/// Maximum depth of nested calls
+const FRAMES_MAX: usize = 1024;
+
+/// Function value being called
+#[derive(Debug)]
+enum Callee {
+    /// Function index and the upvalues passed after the arguments
+    Closure(u16, Vec<Value>),
+    Native(u16),
+}
+
 #[derive(Debug)]
 pub struct Vm<'p> {
     program: &'p Program,
     frames: Vec<CallFrame<'p>>,
     stack: Vec<Value>,
-    // memory: Vec<Object>,
+    /// Heap objects referenced by `Value::Obj`, never freed for now
+    memory: Vec<Object>,
 }
 
 impl<'p> Vm<'p> {
@@ -16,7 +28,7 @@ impl<'p> Vm<'p> {
             program,
             frames: Vec::new(),
             stack: Vec::new(),
-            // memory: Vec::new(),
+            memory: Vec::new(),
         }
     }
 
@@ -73,6 +85,115 @@ impl<'p> Vm<'p> {
         self.push(Value::Bool(v))
     }
 
+    /// Store an object on the heap, natives use it to return strings, arrays and the like
+    pub fn alloc(&mut self, object: Object) -> Value {
+        self.memory.push(object);
+        Value::Obj(self.memory.len() - 1)
+    }
+
+    /// Tuple, struct or variant payload, all store their fields by position
+    fn pop_fields(&mut self) -> Result<&[Value]> {
+        let value = self.pop()?;
+        match value {
+            Value::Obj(r) => match self.memory.get(r) {
+                Some(
+                    Object::Tuple(fields) | Object::Struct(fields) | Object::Variant { fields, .. },
+                ) => Ok(fields),
+                _ => Err(Error::TypeMismatch("Expected tuple, struct or variant")),
+            },
+            _ => Err(Error::TypeMismatch("Expected tuple, struct or variant")),
+        }
+    }
+
+    /// Function index and a copy of the upvalues of a closure, or a host function
+    fn callee(&self, value: Value) -> Result<Callee> {
+        match value {
+            Value::Obj(r) => match self.memory.get(r) {
+                Some(Object::Closure { function, upvalues }) => {
+                    Ok(Callee::Closure(*function, upvalues.clone()))
+                }
+                Some(Object::NativeFunction(idx)) => Ok(Callee::Native(*idx)),
+                _ => Err(Error::TypeMismatch("Expected function")),
+            },
+            _ => Err(Error::TypeMismatch("Expected function")),
+        }
+    }
+
+    /// Heap object a value refers to, natives use it to read their arguments
+    pub fn get_object(&self, value: Value) -> Result<&Object> {
+        match value {
+            Value::Obj(r) => self
+                .memory
+                .get(r)
+                .ok_or(Error::TypeMismatch("Expected object")),
+            _ => Err(Error::TypeMismatch("Expected object")),
+        }
+    }
+
+    fn pop_tag(&mut self) -> Result<u16> {
+        let value = self.pop()?;
+        match value {
+            Value::Obj(r) => match self.memory.get(r) {
+                Some(Object::Variant { tag, .. }) => Ok(*tag),
+                _ => Err(Error::TypeMismatch("Expected variant")),
+            },
+            _ => Err(Error::TypeMismatch("Expected variant")),
+        }
+    }
+    fn pop_struct(&mut self) -> Result<&mut Vec<Value>> {
+        let value = self.pop()?;
+        match value {
+            Value::Obj(r) => match self.memory.get_mut(r) {
+                Some(Object::Struct(fields)) => Ok(fields),
+                _ => Err(Error::TypeMismatch("Expected struct")),
+            },
+            _ => Err(Error::TypeMismatch("Expected struct")),
+        }
+    }
+    fn pop_str(&mut self) -> Result<&str> {
+        let value = self.pop()?;
+        match value {
+            Value::Obj(r) => match self.memory.get(r) {
+                Some(Object::String(s)) => Ok(s),
+                _ => Err(Error::TypeMismatch("Expected string")),
+            },
+            _ => Err(Error::TypeMismatch("Expected string")),
+        }
+    }
+    /// Operands of a binary string operation, the right one is on top
+    fn pop_str_pair(&mut self) -> Result<(String, String)> {
+        let rhs = self.pop_str()?.to_owned();
+        let lhs = self.pop_str()?.to_owned();
+        Ok((lhs, rhs))
+    }
+    fn push_str(&mut self, s: String) -> Result<()> {
+        let value = self.alloc(Object::String(s));
+        self.push(value)
+    }
+
+    fn pop_array(&mut self) -> Result<&mut Vec<Value>> {
+        let value = self.pop()?;
+        match value {
+            Value::Obj(r) => match self.memory.get_mut(r) {
+                Some(Object::Array(elements)) => Ok(elements),
+                _ => Err(Error::TypeMismatch("Expected array")),
+            },
+            _ => Err(Error::TypeMismatch("Expected array")),
+        }
+    }
+    /// Array and element index below it on the stack, bounds checked
+    fn pop_element(&mut self) -> Result<(&mut Vec<Value>, usize)> {
+        let index = self.pop_int64()?;
+        let array = self.pop_array()?;
+        match usize::try_from(index) {
+            Ok(idx) if idx < array.len() => Ok((array, idx)),
+            _ => Err(Error::IndexOutOfBounds {
+                index,
+                len: array.len(),
+            }),
+        }
+    }
+
     fn pop_frame(&mut self) -> Result<()> {
         match self.frames.pop() {
             Some(_) => Ok(()),
@@ -80,22 +201,71 @@ impl<'p> Vm<'p> {
         }
     }
     fn push_frame(&mut self, function: &'p Function) -> Result<()> {
-        let frame = CallFrame::new(function, self.stack.len() - function.arity as usize);
+        if self.frames.len() >= FRAMES_MAX {
+            return Err(Error::StackOverflow);
+        }
+        let stack_base = self
+            .stack
+            .len()
+            .checked_sub(function.arity as usize)
+            .ok_or(Error::StackUnderflow)?;
+        let frame = CallFrame::new(function, stack_base);
         self.frames.push(frame);
+        for _ in 0..function.locals {
+            self.push(Value::Unit)?;
+        }
         Ok(())
     }
 
-    pub fn run(&mut self) -> Result<i64> {
+    /// Call a host function with the top `argc` values as its arguments
+    fn call_native(&mut self, idx: u16, argc: u16) -> Result<()> {
+        let start = self
+            .stack
+            .len()
+            .checked_sub(argc as usize)
+            .ok_or(Error::StackUnderflow)?;
+        let args = self.stack.split_off(start);
+        let native = self.program.get_native(idx);
+        let value = (native.function)(self, &args)?;
+        self.push(value)
+    }
+
+    /// Run the entry function to completion, errors are located at the failing instruction
+    pub fn run(&mut self) -> std::result::Result<i64, RuntimeError> {
+        self.execute().map_err(|error| self.locate(error))
+    }
+
+    /// The last byte read belongs to the instruction being executed
+    fn locate(&self, error: Error) -> RuntimeError {
+        let (function, span) = match self.frames.last() {
+            Some(frame) => {
+                let chunk = &frame.function.chunk;
+                let span = frame.ip.checked_sub(1).map(|at| chunk.get_span(at));
+                (frame.function, span)
+            }
+            None => (&self.program.functions[self.program.entry], None),
+        };
+        let span = span.filter(|span| span.file < self.program.files.len());
+        let line = span.map_or(0, |span| self.program.files[span.file].line(span.start));
+        RuntimeError {
+            error,
+            function: function.name.clone(),
+            line,
+            span,
+        }
+    }
+
+    fn execute(&mut self) -> Result<i64> {
         use OpCode::*;
 
         let entry_function = &self.program.functions[self.program.entry];
-        let _ = self.push_frame(entry_function);
+        self.push_frame(entry_function)?;
         self.trace_call_enter(entry_function);
 
         while let Ok(frame) = self.current_frame_mut() {
             let op = frame.read_opcode()?;
 
-            let _ = match op {
+            match op {
                 Const => {
                     let idx = frame.read_u16()?;
                     let value = frame.get_const(idx);
@@ -103,29 +273,116 @@ impl<'p> Vm<'p> {
                     self.push(value)
                 }
 
+                True => {
+                    self.trace_op(True);
+                    self.push_bool(true)
+                }
+                False => {
+                    self.trace_op(False);
+                    self.push_bool(false)
+                }
+                StrConst => {
+                    let idx = frame.read_u16()?;
+                    let s = frame.get_string(idx);
+                    self.trace_op_u16(StrConst, idx);
+                    self.push_str(s.to_owned())
+                }
+
+                GetLocal => {
+                    let slot = frame.read_u16()?;
+                    let idx = frame.stack_base + slot as usize;
+                    self.trace_op_u16(GetLocal, slot);
+                    let value = *self.stack.get(idx).ok_or(Error::InvalidLocal(slot))?;
+                    self.push(value)
+                }
+                SetLocal => {
+                    let slot = frame.read_u16()?;
+                    let idx = frame.stack_base + slot as usize;
+                    self.trace_op_u16(SetLocal, slot);
+                    let value = self.pop()?;
+                    let target = self.stack.get_mut(idx).ok_or(Error::InvalidLocal(slot))?;
+                    *target = value;
+                    Ok(())
+                }
+
+                Jump => {
+                    let offset = frame.read_u16()?;
+                    let jumped = frame.jump(offset);
+                    self.trace_op_u16(Jump, offset);
+                    jumped
+                }
+                JumpIfFalse => {
+                    let offset = frame.read_u16()?;
+                    self.trace_op_u16(JumpIfFalse, offset);
+                    match self.pop_bool()? {
+                        true => Ok(()),
+                        false => self.current_frame_mut()?.jump(offset),
+                    }
+                }
+
+                Loop => {
+                    let offset = frame.read_u16()?;
+                    let jumped = frame.loop_back(offset);
+                    self.trace_op_u16(Loop, offset);
+                    jumped
+                }
+
                 I64Add => {
                     let rhs = self.pop_int64()?;
                     let lhs = self.pop_int64()?;
                     self.trace_op(I64Add);
-                    self.push_int64(lhs + rhs)
+                    let value = lhs.checked_add(rhs).ok_or(Error::IntegerOverflow("add"))?;
+                    self.push_int64(value)
                 }
                 I64Sub => {
                     let rhs = self.pop_int64()?;
                     let lhs = self.pop_int64()?;
                     self.trace_op(I64Sub);
-                    self.push_int64(lhs - rhs)
+                    let value = lhs
+                        .checked_sub(rhs)
+                        .ok_or(Error::IntegerOverflow("subtract"))?;
+                    self.push_int64(value)
                 }
                 I64Mul => {
                     let rhs = self.pop_int64()?;
                     let lhs = self.pop_int64()?;
                     self.trace_op(I64Mul);
-                    self.push_int64(lhs * rhs)
+                    let value = lhs
+                        .checked_mul(rhs)
+                        .ok_or(Error::IntegerOverflow("multiply"))?;
+                    self.push_int64(value)
                 }
                 I64Div => {
                     let rhs = self.pop_int64()?;
                     let lhs = self.pop_int64()?;
                     self.trace_op(I64Div);
-                    self.push_int64(lhs / rhs)
+                    if rhs == 0 {
+                        return Err(Error::DivisionByZero);
+                    }
+                    // `i64::MIN / -1` is the only quotient out of range
+                    let value = lhs
+                        .checked_div(rhs)
+                        .ok_or(Error::IntegerOverflow("divide"))?;
+                    self.push_int64(value)
+                }
+                I64Rem => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64Rem);
+                    if rhs == 0 {
+                        return Err(Error::DivisionByZero);
+                    }
+                    // sign follows the dividend, `i64::MIN % -1` is 0
+                    self.push_int64(lhs.wrapping_rem(rhs))
+                }
+                I64Neg => {
+                    let value = self.pop_int64()?;
+                    self.trace_op(I64Neg);
+                    // `-i64::MIN` is out of range
+                    let value = value
+                        .checked_neg()
+                        .ok_or(Error::IntegerOverflow("negate"))?;
+                    self.push_int64(value)
                 }
                 I64Equal => {
                     let rhs = self.pop_int64()?;
@@ -168,26 +425,45 @@ impl<'p> Vm<'p> {
                     let rhs = self.pop_uint64()?;
                     let lhs = self.pop_uint64()?;
                     self.trace_op(OpCode::U64Add);
-                    self.push_uint64(lhs + rhs)
+                    let value = lhs.checked_add(rhs).ok_or(Error::IntegerOverflow("add"))?;
+                    self.push_uint64(value)
                 }
                 OpCode::U64Sub => {
                     let rhs = self.pop_uint64()?;
                     let lhs = self.pop_uint64()?;
                     self.trace_op(OpCode::U64Sub);
-                    self.push_uint64(lhs - rhs)
+                    let value = lhs
+                        .checked_sub(rhs)
+                        .ok_or(Error::IntegerOverflow("subtract"))?;
+                    self.push_uint64(value)
                 }
                 OpCode::U64Mul => {
                     let rhs = self.pop_uint64()?;
                     let lhs = self.pop_uint64()?;
                     self.trace_op(OpCode::U64Mul);
-                    self.push_uint64(lhs * rhs)
+                    let value = lhs
+                        .checked_mul(rhs)
+                        .ok_or(Error::IntegerOverflow("multiply"))?;
+                    self.push_uint64(value)
                 }
                 OpCode::U64Div => {
                     let rhs = self.pop_uint64()?;
                     let lhs = self.pop_uint64()?;
                     self.trace_op(OpCode::U64Div);
+                    if rhs == 0 {
+                        return Err(Error::DivisionByZero);
+                    }
                     self.push_uint64(lhs / rhs)
                 }
+                U64Rem => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64Rem);
+                    if rhs == 0 {
+                        return Err(Error::DivisionByZero);
+                    }
+                    self.push_uint64(lhs % rhs)
+                }
                 U64Equal => {
                     let rhs = self.pop_uint64()?;
                     let lhs = self.pop_uint64()?;
@@ -249,6 +525,18 @@ impl<'p> Vm<'p> {
                     self.trace_op(OpCode::F64Div);
                     self.push_float64(lhs / rhs)
                 }
+                F64Rem => {
+                    let rhs = self.pop_float64()?;
+                    let lhs = self.pop_float64()?;
+                    self.trace_op(F64Rem);
+                    // fmod: sign follows the dividend, NaN for zero divisor
+                    self.push_float64(lhs % rhs)
+                }
+                F64Neg => {
+                    let value = self.pop_float64()?;
+                    self.trace_op(F64Neg);
+                    self.push_float64(-value)
+                }
                 F64Equal => {
                     let rhs = self.pop_float64()?;
                     let lhs = self.pop_float64()?;
@@ -286,20 +574,394 @@ impl<'p> Vm<'p> {
                     self.push_bool(lhs >= rhs)
                 }
 
+                StrConcat => {
+                    let (lhs, rhs) = self.pop_str_pair()?;
+                    self.trace_op(StrConcat);
+                    self.push_str(lhs + &rhs)
+                }
+                StrEqual => {
+                    let (lhs, rhs) = self.pop_str_pair()?;
+                    self.trace_op(StrEqual);
+                    self.push_bool(lhs == rhs)
+                }
+                StrNotEqual => {
+                    let (lhs, rhs) = self.pop_str_pair()?;
+                    self.trace_op(StrNotEqual);
+                    self.push_bool(lhs != rhs)
+                }
+                StrLess => {
+                    let (lhs, rhs) = self.pop_str_pair()?;
+                    self.trace_op(StrLess);
+                    self.push_bool(lhs < rhs)
+                }
+                StrLessEqual => {
+                    let (lhs, rhs) = self.pop_str_pair()?;
+                    self.trace_op(StrLessEqual);
+                    self.push_bool(lhs <= rhs)
+                }
+                StrGreater => {
+                    let (lhs, rhs) = self.pop_str_pair()?;
+                    self.trace_op(StrGreater);
+                    self.push_bool(lhs > rhs)
+                }
+                StrGreaterEqual => {
+                    let (lhs, rhs) = self.pop_str_pair()?;
+                    self.trace_op(StrGreaterEqual);
+                    self.push_bool(lhs >= rhs)
+                }
+                StrLen => {
+                    self.trace_op(StrLen);
+                    // length in bytes, like Rust
+                    let len = self.pop_str()?.len();
+                    self.push_int64(len as i64)
+                }
+
                 BoolNot => {
                     let b = self.pop_bool()?;
                     self.trace_op(BoolNot);
                     self.push_bool(!b)
                 }
 
+                MakeTuple => {
+                    let len = frame.read_u16()?;
+                    self.trace_op_u16(MakeTuple, len);
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(len as usize)
+                        .ok_or(Error::StackUnderflow)?;
+                    let elements = self.stack.split_off(start);
+                    let tuple = self.alloc(Object::Tuple(elements));
+                    self.push(tuple)
+                }
+                GetField => {
+                    let index = frame.read_u16()?;
+                    self.trace_op_u16(GetField, index);
+                    let fields = self.pop_fields()?;
+                    let value = *fields
+                        .get(index as usize)
+                        .ok_or(Error::InvalidField(index))?;
+                    self.push(value)
+                }
+                SetField => {
+                    let index = frame.read_u16()?;
+                    self.trace_op_u16(SetField, index);
+                    let value = self.pop()?;
+                    let fields = self.pop_struct()?;
+                    let field = fields
+                        .get_mut(index as usize)
+                        .ok_or(Error::InvalidField(index))?;
+                    *field = value;
+                    Ok(())
+                }
+                MakeStruct => {
+                    let len = frame.read_u16()?;
+                    self.trace_op_u16(MakeStruct, len);
+                    // fields are filled in by `SetField`
+                    let fields = vec![Value::Unit; len as usize];
+                    let object = self.alloc(Object::Struct(fields));
+                    self.push(object)
+                }
+                Unpack => {
+                    let len = frame.read_u16()?;
+                    self.trace_op_u16(Unpack, len);
+                    let fields = self.pop_fields()?;
+                    if fields.len() != len as usize {
+                        return Err(Error::TypeMismatch("Unexpected field count"));
+                    }
+                    let fields = fields.to_vec();
+                    self.stack.extend(fields);
+                    Ok(())
+                }
+                MakeVariant => {
+                    let tag = frame.read_u16()?;
+                    let len = frame.read_u16()?;
+                    self.trace_op_u16_u16(MakeVariant, tag, len);
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(len as usize)
+                        .ok_or(Error::StackUnderflow)?;
+                    let fields = self.stack.split_off(start);
+                    let variant = self.alloc(Object::Variant { tag, fields });
+                    self.push(variant)
+                }
+                MakeClosure => {
+                    let function = frame.read_u16()?;
+                    let len = frame.read_u16()?;
+                    self.trace_op_u16_u16(MakeClosure, function, len);
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(len as usize)
+                        .ok_or(Error::StackUnderflow)?;
+                    let upvalues = self.stack.split_off(start);
+                    let closure = self.alloc(Object::Closure { function, upvalues });
+                    self.push(closure)
+                }
+                MakeNative => {
+                    let idx = frame.read_u16()?;
+                    self.trace_op_u16(MakeNative, idx);
+                    let native = self.alloc(Object::NativeFunction(idx));
+                    self.push(native)
+                }
+                IsVariant => {
+                    let tag = frame.read_u16()?;
+                    self.trace_op_u16(IsVariant, tag);
+                    let found = self.pop_tag()?;
+                    self.push(Value::Bool(found == tag))
+                }
+
+                MakeArray => {
+                    let len = frame.read_u16()?;
+                    self.trace_op_u16(MakeArray, len);
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(len as usize)
+                        .ok_or(Error::StackUnderflow)?;
+                    let elements = self.stack.split_off(start);
+                    let array = self.alloc(Object::Array(elements));
+                    self.push(array)
+                }
+                GetIndex => {
+                    self.trace_op(GetIndex);
+                    let (array, idx) = self.pop_element()?;
+                    let value = array[idx];
+                    self.push(value)
+                }
+                SetIndex => {
+                    self.trace_op(SetIndex);
+                    let value = self.pop()?;
+                    let (array, idx) = self.pop_element()?;
+                    array[idx] = value;
+                    Ok(())
+                }
+                ArrayPush => {
+                    self.trace_op(ArrayPush);
+                    let value = self.pop()?;
+                    self.pop_array()?.push(value);
+                    self.push(Value::Unit)
+                }
+                ArrayPop => {
+                    self.trace_op(ArrayPop);
+                    let value = self.pop_array()?.pop().ok_or(Error::PopEmpty)?;
+                    self.push(value)
+                }
+                ArrayLen => {
+                    self.trace_op(ArrayLen);
+                    let len = self.pop_array()?.len();
+                    self.push_int64(len as i64)
+                }
+
+                I64WrappingAdd => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64WrappingAdd);
+                    self.push_int64(lhs.wrapping_add(rhs))
+                }
+                I64WrappingSub => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64WrappingSub);
+                    self.push_int64(lhs.wrapping_sub(rhs))
+                }
+                I64WrappingMul => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64WrappingMul);
+                    self.push_int64(lhs.wrapping_mul(rhs))
+                }
+                I64SaturatingAdd => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64SaturatingAdd);
+                    self.push_int64(lhs.saturating_add(rhs))
+                }
+                I64SaturatingSub => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64SaturatingSub);
+                    self.push_int64(lhs.saturating_sub(rhs))
+                }
+                I64SaturatingMul => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64SaturatingMul);
+                    self.push_int64(lhs.saturating_mul(rhs))
+                }
+
+                U64WrappingAdd => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64WrappingAdd);
+                    self.push_uint64(lhs.wrapping_add(rhs))
+                }
+                U64WrappingSub => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64WrappingSub);
+                    self.push_uint64(lhs.wrapping_sub(rhs))
+                }
+                U64WrappingMul => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64WrappingMul);
+                    self.push_uint64(lhs.wrapping_mul(rhs))
+                }
+                U64SaturatingAdd => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64SaturatingAdd);
+                    self.push_uint64(lhs.saturating_add(rhs))
+                }
+                U64SaturatingSub => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64SaturatingSub);
+                    self.push_uint64(lhs.saturating_sub(rhs))
+                }
+                U64SaturatingMul => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64SaturatingMul);
+                    self.push_uint64(lhs.saturating_mul(rhs))
+                }
+
+                I64BitAnd => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64BitAnd);
+                    self.push_int64(lhs & rhs)
+                }
+                I64BitOr => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64BitOr);
+                    self.push_int64(lhs | rhs)
+                }
+                I64BitXor => {
+                    let rhs = self.pop_int64()?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64BitXor);
+                    self.push_int64(lhs ^ rhs)
+                }
+                I64Shl => {
+                    let rhs = shift_amount(self.pop_int64()?)?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64Shl);
+                    self.push_int64(lhs << rhs)
+                }
+                I64Shr => {
+                    let rhs = shift_amount(self.pop_int64()?)?;
+                    let lhs = self.pop_int64()?;
+                    self.trace_op(I64Shr);
+                    // arithmetic, the sign bit is copied in
+                    self.push_int64(lhs >> rhs)
+                }
+                I64BitNot => {
+                    let value = self.pop_int64()?;
+                    self.trace_op(I64BitNot);
+                    self.push_int64(!value)
+                }
+
+                U64BitAnd => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64BitAnd);
+                    self.push_uint64(lhs & rhs)
+                }
+                U64BitOr => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64BitOr);
+                    self.push_uint64(lhs | rhs)
+                }
+                U64BitXor => {
+                    let rhs = self.pop_uint64()?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64BitXor);
+                    self.push_uint64(lhs ^ rhs)
+                }
+                U64Shl => {
+                    let rhs = shift_amount(self.pop_uint64()?)?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64Shl);
+                    self.push_uint64(lhs << rhs)
+                }
+                U64Shr => {
+                    let rhs = shift_amount(self.pop_uint64()?)?;
+                    let lhs = self.pop_uint64()?;
+                    self.trace_op(U64Shr);
+                    // logical, zeros are shifted in
+                    self.push_uint64(lhs >> rhs)
+                }
+                U64BitNot => {
+                    let value = self.pop_uint64()?;
+                    self.trace_op(U64BitNot);
+                    self.push_uint64(!value)
+                }
+
+                I64ToU64 => {
+                    self.trace_op(I64ToU64);
+                    let i = self.pop_int64()?;
+                    self.push_uint64(i as u64)
+                }
+                I64ToF64 => {
+                    self.trace_op(I64ToF64);
+                    let i = self.pop_int64()?;
+                    self.push_float64(i as f64)
+                }
+                U64ToI64 => {
+                    self.trace_op(U64ToI64);
+                    let u = self.pop_uint64()?;
+                    self.push_int64(u as i64)
+                }
+                U64ToF64 => {
+                    self.trace_op(U64ToF64);
+                    let u = self.pop_uint64()?;
+                    self.push_float64(u as f64)
+                }
+                // `as` from float to integer saturates and maps NaN to 0
+                F64ToI64 => {
+                    self.trace_op(F64ToI64);
+                    let f = self.pop_float64()?;
+                    self.push_int64(f as i64)
+                }
+                F64ToU64 => {
+                    self.trace_op(F64ToU64);
+                    let f = self.pop_float64()?;
+                    self.push_uint64(f as u64)
+                }
+
+                Dup => {
+                    self.trace_op(Dup);
+                    let value = *self.stack.last().ok_or(Error::StackUnderflow)?;
+                    self.push(value)
+                }
+                Dup2 => {
+                    self.trace_op(Dup2);
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(2)
+                        .ok_or(Error::StackUnderflow)?;
+                    self.stack.extend_from_within(start..);
+                    Ok(())
+                }
                 Pop => {
                     self.trace_op(Pop);
-                    dbg!(self.pop()).map(|_| {})
+                    self.pop().map(|_| {})
                 }
                 Return => {
                     let func = frame.function;
+                    let stack_base = frame.stack_base;
                     self.trace_call_exit(func);
-                    self.pop_frame()
+                    // drop arguments, locals and temporaries of the callee
+                    let value = self.pop()?;
+                    self.stack.truncate(stack_base);
+                    self.pop_frame()?;
+                    self.push(value)
                 }
                 Call => {
                     let idx = frame.read_u16()?;
@@ -307,23 +969,67 @@ impl<'p> Vm<'p> {
                     self.trace_call_enter(func);
                     self.push_frame(func)
                 }
+                CallValue => {
+                    let argc = frame.read_u16()?;
+                    self.trace_op_u16(CallValue, argc);
+                    // the callee sits below its arguments, upvalues follow them
+                    let at = self
+                        .stack
+                        .len()
+                        .checked_sub(argc as usize + 1)
+                        .ok_or(Error::StackUnderflow)?;
+                    let callee = self.stack.remove(at);
+                    match self.callee(callee)? {
+                        Callee::Closure(idx, upvalues) => {
+                            self.stack.extend(upvalues);
+                            let func = self.program.get_function(idx);
+                            self.trace_call_enter(func);
+                            self.push_frame(func)
+                        }
+                        Callee::Native(idx) => self.call_native(idx, argc),
+                    }
+                }
+                CallNative => {
+                    let idx = frame.read_u16()?;
+                    let argc = frame.read_u16()?;
+                    self.trace_op_u16_u16(CallNative, idx, argc);
+                    self.call_native(idx, argc)
+                }
+                Panic => {
+                    self.trace_op(Panic);
+                    Err(Error::Panic)
+                }
+                Print => {
+                    self.trace_op(Print);
+                    let s = self.pop_str()?;
+                    println!("{s}");
+                    self.push(Value::Unit)
+                }
 
                 _ => {
                     unimplemented!()
                 }
-            };
+            }?;
         }
 
-        // main always return int64
-        println!("Leaving main");
-        let value = dbg!(self.pop())?;
+        // main returns an exit code or nothing
+        let value = self.pop()?;
         match value {
             Value::Int64(i) => Ok(i),
+            Value::Unit => Ok(0),
             _ => Err(Error::TypeMismatch("Expected int64")),
         }
     }
 }
 
+/// Shifts by 64 bits or more, or by a negative amount, are rejected instead of masked
+pub(crate) fn shift_amount<T: TryInto<u32>>(amount: T) -> Result<u32> {
+    match amount.try_into() {
+        Ok(amount) if amount < u64::BITS => Ok(amount),
+        _ => Err(Error::ShiftOverflow),
+    }
+}
+
 impl<'p> Vm<'p> {
     fn trace_op(&self, _op: OpCode) {
         #[cfg(feature = "trace_vm")]
@@ -339,6 +1045,13 @@ impl<'p> Vm<'p> {
         }
     }
 
+    fn trace_op_u16_u16(&self, _op: OpCode, _first: u16, _second: u16) {
+        #[cfg(feature = "trace_vm")]
+        {
+            println!("{_op} {_first:#08x} {_second:#08x}");
+        }
+    }
+
     fn trace_call_enter(&self, _func: &'p Function) {
         #[cfg(feature = "trace_vm")]
         {
@@ -357,30 +1070,134 @@ impl<'p> Vm<'p> {
 #[cfg(test)]
 mod tests {
     use crate::bytecode::Chunk;
+    use crate::grammar::{SourceFile, Span};
 
     use super::*;
 
-    #[test]
-    fn i64_add_two_consts() {
-        let mut chunk = Chunk::new();
-        chunk.add_int64(4, 0);
-        chunk.add_int64(7, 0);
-        chunk.add_instruction(OpCode::I64Add, 0);
-        chunk.add_instruction(OpCode::Return, 1);
+    /// Source the spans of the test chunks point into
+    const SOURCE: &str = "fn main() -> i64 {\n    7 / 0\n}\n";
 
-        let main_fn = Function {
+    /// Run the chunk as the body of `main`
+    fn run_chunk(chunk: Chunk, locals: u16) -> std::result::Result<i64, RuntimeError> {
+        let mut program = Program::new();
+        program.files.push(SourceFile::new("main.rl", SOURCE));
+        program.functions.push(Function {
             name: "main".to_string(),
             chunk,
             arity: 0,
-        };
+            locals,
+        });
+        Vm::from(&program).run()
+    }
 
-        let mut program = Program::new();
-        program.functions.push(main_fn);
-        program.entry = 0;
+    #[test]
+    fn i64_add_two_consts() {
+        let mut chunk = Chunk::new();
+        chunk.add_int64(4, Span::default());
+        chunk.add_int64(7, Span::default());
+        chunk.add_instruction(OpCode::I64Add, Span::default());
+        chunk.add_instruction(OpCode::Return, Span::default());
 
-        let mut vm = Vm::from(&program);
-        let result = vm.run().expect("vm run failed");
+        let result = run_chunk(chunk, 0).expect("vm run failed");
 
         assert_eq!(result, 11);
     }
+
+    #[test]
+    fn locals_are_relative_to_stack_base() {
+        let mut chunk = Chunk::new();
+        chunk.add_int64(4, Span::default());
+        chunk.add_instruction_u16(OpCode::SetLocal, 0, Span::default());
+        chunk.add_instruction_u16(OpCode::GetLocal, 0, Span::default());
+        chunk.add_instruction_u16(OpCode::SetLocal, 1, Span::default());
+        chunk.add_instruction_u16(OpCode::GetLocal, 1, Span::default());
+        chunk.add_instruction(OpCode::Return, Span::default());
+
+        let result = run_chunk(chunk, 2).expect("vm run failed");
+
+        assert_eq!(result, 4);
+    }
+
+    #[test]
+    fn jump_if_false_skips_then_branch() {
+        let mut chunk = Chunk::new();
+        chunk.add_bool(false, Span::default());
+        let to_else = chunk.add_jump(OpCode::JumpIfFalse, Span::default());
+        chunk.add_int64(1, Span::default());
+        let to_end = chunk.add_jump(OpCode::Jump, Span::default());
+        chunk.patch_jump(to_else);
+        chunk.add_int64(2, Span::default());
+        chunk.patch_jump(to_end);
+        chunk.add_instruction(OpCode::Return, Span::default());
+
+        let result = run_chunk(chunk, 0).expect("vm run failed");
+
+        assert_eq!(result, 2);
+    }
+
+    #[test]
+    fn loop_before_chunk_start_is_invalid() {
+        let mut chunk = Chunk::new();
+        chunk.add_instruction_u16(OpCode::Loop, 100, Span::default());
+        chunk.add_instruction(OpCode::Return, Span::default());
+
+        let result = run_chunk(chunk, 0).map_err(|trap| trap.error);
+
+        assert!(matches!(result, Err(Error::InvalidJumpTarget)));
+    }
+
+    #[test]
+    fn integer_remainder_by_zero_is_an_error() {
+        let mut chunk = Chunk::new();
+        chunk.add_int64(7, Span::default());
+        chunk.add_int64(0, Span::default());
+        chunk.add_instruction(OpCode::I64Rem, Span::default());
+        chunk.add_instruction(OpCode::Return, Span::default());
+
+        let result = run_chunk(chunk, 0).map_err(|trap| trap.error);
+
+        assert!(matches!(result, Err(Error::DivisionByZero)));
+    }
+
+    #[test]
+    fn float_to_int_casts_truncate_and_saturate() {
+        let cast = |f: f64| {
+            let mut chunk = Chunk::new();
+            chunk.add_float64(f, Span::default());
+            chunk.add_instruction(OpCode::F64ToI64, Span::default());
+            chunk.add_instruction(OpCode::Return, Span::default());
+            run_chunk(chunk, 0).expect("vm run failed")
+        };
+
+        assert_eq!(cast(2.9), 2);
+        assert_eq!(cast(-2.9), -2);
+        assert_eq!(cast(1e300), i64::MAX);
+        assert_eq!(cast(f64::NEG_INFINITY), i64::MIN);
+        assert_eq!(cast(f64::NAN), 0);
+    }
+
+    #[test]
+    fn division_errors_are_located() {
+        let divide = |lhs: i64, rhs: i64| {
+            let mut chunk = Chunk::new();
+            chunk.add_int64(lhs, Span::new(0, 23, 24));
+            chunk.add_int64(rhs, Span::new(0, 27, 28));
+            chunk.add_instruction(OpCode::I64Div, Span::new(0, 23, 28));
+            chunk.add_instruction(OpCode::Return, Span::new(0, 29, 30));
+            run_chunk(chunk, 0)
+        };
+
+        assert_eq!(divide(-7, 2).expect("vm run failed"), -3);
+
+        let trap = divide(1, 0).expect_err("division by zero");
+        assert!(matches!(trap.error, Error::DivisionByZero));
+        assert_eq!((trap.function.as_str(), trap.line), ("main", 2));
+
+        let trap = divide(i64::MIN, -1).expect_err("overflow");
+        assert!(matches!(trap.error, Error::IntegerOverflow(_)));
+        assert_eq!(
+            trap.to_string(),
+            "attempt to divide with overflow in `main` at line 2"
+        );
+    }
 }